@@ -0,0 +1,134 @@
+use csv::{QuoteStyle, Terminator, WriterBuilder};
+use serde::Deserialize;
+use serde_dynamo::from_items;
+
+use super::{ArchiveEntry, ArchiveSource, ARCHIVE_SK};
+use crate::utils::ddb::ddb_query_partition;
+use crate::{AResult, AppState};
+
+// file headers understood by Anki's importer since 2.1.54; the guid column makes
+// importing a newer export update the notes instead of duplicating them
+const ANKI_HEADERS: &str = "#separator:tab\n#html:false\n#tags column:3\n#guid column:4\n";
+
+#[derive(Deserialize)]
+pub struct AnkiExportParams {
+    pub category: Option<String>,
+}
+
+/// Anki tags are separated by spaces, so spaces within a category become underscores.
+fn anki_tag(category: &str) -> String {
+    category.split_whitespace().collect::<Vec<&str>>().join("_")
+}
+
+/// Back of a card: where the quote comes from, e.g. "Seneca, Letters from a Stoic (p. 42)".
+fn citation(source: &ArchiveSource) -> String {
+    let mut citation = [&source.author, &source.title]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect::<Vec<String>>()
+        .join(", ");
+    if let Some(location) = &source.location {
+        citation = format!("{} ({})", citation, location).trim().to_string();
+    }
+    if let Some(url) = &source.url {
+        citation = format!("{}\n{}", citation, url).trim().to_string();
+    }
+    citation
+}
+
+/// Tab-separated notes with the content on the front, the source on the back and
+/// categories as tags.
+pub fn to_anki_tsv(entries: &[ArchiveEntry]) -> AResult<String> {
+    let mut writer = WriterBuilder::new()
+        .delimiter(b'\t')
+        .quote_style(QuoteStyle::Necessary)
+        .terminator(Terminator::Any(b'\n'))
+        .from_writer(ANKI_HEADERS.as_bytes().to_vec());
+
+    for entry in entries {
+        let back = entry.source.as_ref().map(citation).unwrap_or_default();
+        let tags = entry
+            .categories
+            .iter()
+            .flatten()
+            .map(|c| anki_tag(c))
+            .collect::<Vec<String>>()
+            .join(" ");
+        writer.write_record([&entry.content, &back, &tags, &entry.sk])?;
+    }
+
+    let bytes = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+impl ArchiveEntry {
+    pub async fn ddb_find_for_export(
+        state: &AppState,
+        category: Option<&str>,
+    ) -> AResult<Vec<ArchiveEntry>> {
+        match category {
+            Some(category) => ArchiveEntry::ddb_find_by_category(state, category.trim()).await,
+            None => Ok(from_items(ddb_query_partition(state, ARCHIVE_SK).await?)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::ArchiveEntryFC;
+
+    fn entry(content: &str, categories: &[&str], source: Option<ArchiveSource>) -> ArchiveEntry {
+        let mut entry = ArchiveEntry::from(ArchiveEntryFC {
+            content: content.to_string(),
+            categories: Some(categories.iter().map(|c| c.to_string()).collect()),
+            source,
+        });
+        entry.sk = String::from("2024-05-01T08:30:00+02:00_01HWSDGZNV8D3Q6V2M1PJ4X7TR");
+        entry
+    }
+
+    #[test]
+    fn test_citation() {
+        let mut source = ArchiveSource {
+            author: Some(String::from("Seneca")),
+            title: Some(String::from("Letters from a Stoic")),
+            location: Some(String::from("p. 42")),
+            ..Default::default()
+        };
+        assert_eq!(citation(&source), "Seneca, Letters from a Stoic (p. 42)");
+
+        source.author = None;
+        source.location = None;
+        source.url = Some(String::from("https://example.com/letters"));
+        assert_eq!(
+            citation(&source),
+            "Letters from a Stoic\nhttps://example.com/letters"
+        );
+    }
+
+    #[test]
+    fn test_to_anki_tsv() {
+        let source = ArchiveSource {
+            author: Some(String::from("Seneca")),
+            ..Default::default()
+        };
+        let entries = vec![
+            entry("Festina lente", &["latin", "decision making"], None),
+            entry("He said \"wait\"\tthen\nleft", &[], Some(source)),
+        ];
+
+        let tsv = to_anki_tsv(&entries).unwrap();
+        let sk = "2024-05-01T08:30:00+02:00_01HWSDGZNV8D3Q6V2M1PJ4X7TR";
+        assert_eq!(
+            tsv,
+            format!(
+                "{}Festina lente\t\tlatin decision_making\t{}\n\
+                 \"He said \"\"wait\"\"\tthen\nleft\"\tSeneca\t\t{}\n",
+                ANKI_HEADERS, sk, sk
+            )
+        );
+        assert_eq!(to_anki_tsv(&[]).unwrap(), ANKI_HEADERS);
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::{AttributeValue, WriteRequest};
+use serde::{Deserialize, Serialize};
+use serde_dynamo::{from_item, from_items, to_item};
+
+use super::{ArchiveEntry, ARCHIVE_SK};
+use crate::utils::ddb::{
+    ddb_batch_get, ddb_batch_write, ddb_key, ddb_query_partition, delete_request, put_request,
+};
+use crate::{AResult, AppState};
+
+// one item per category with the number of entries in it
+const CATEGORY_CATALOG_PK: &str = "ArchiveCategories";
+
+// an entry changed by someone else while renaming is read again this many times
+const RENAME_ATTEMPTS: usize = 3;
+
+// one item per entry of the category, the sort key is the sort key of the entry
+fn category_pk(category: &str) -> String {
+    format!("ArchiveCategory::{}", category)
+}
+
+#[derive(Serialize, Deserialize)]
+struct CategoryLink {
+    pk: String,
+    sk: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CategoryCatalog {
+    pk: String,
+    sk: String, // category
+    count: i64,
+}
+
+#[derive(Serialize)]
+pub struct CategoryCount {
+    pub category: String,
+    pub count: i64,
+}
+
+#[derive(Deserialize)]
+pub struct CategoryRename {
+    pub from: String,
+    pub to: String, // an existing category merges both
+}
+
+#[derive(Serialize)]
+pub struct CategoryRenameReport {
+    pub renamed: usize,
+    pub conflicts: Vec<String>, // sort keys of entries that kept changing, left as they were
+}
+
+impl CategoryRename {
+    pub fn is_valid(&self) -> bool {
+        let (from, to) = (self.from.trim(), self.to.trim());
+        !from.is_empty() && !to.is_empty() && from != to
+    }
+}
+
+/// Trims categories and drops empty and repeated ones, keeping their order.
+pub fn normalize_categories(categories: Option<Vec<String>>) -> Option<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::new();
+    for category in categories.unwrap_or_default() {
+        let category = category.trim();
+        if !category.is_empty() && !normalized.iter().any(|c| c == category) {
+            normalized.push(category.to_string());
+        }
+    }
+    match normalized.is_empty() {
+        true => None,
+        false => Some(normalized),
+    }
+}
+
+/// Categories after renaming `from` to `to`, or None when `from` isn't among them.
+fn rename_in(categories: &[String], from: &str, to: &str) -> Option<Vec<String>> {
+    if !categories.iter().any(|c| c == from) {
+        return None;
+    }
+    let renamed = categories
+        .iter()
+        .map(|c| match c == from {
+            true => to.to_string(),
+            false => c.clone(),
+        })
+        .collect();
+    normalize_categories(Some(renamed))
+}
+
+async fn ddb_add_count(state: &AppState, category: &str, delta: i64) -> AResult<()> {
+    state
+        .dynamodb_client
+        .update_item()
+        .table_name(&state.table_name)
+        .set_key(Some(ddb_key(CATEGORY_CATALOG_PK, category)))
+        .update_expression("ADD #count :delta")
+        .expression_attribute_names("#count", "count")
+        .expression_attribute_values(":delta", AttributeValue::N(delta.to_string()))
+        .send()
+        .await?;
+    Ok(())
+}
+
+// sets the categories only if they are still `old`; returns false if they aren't
+async fn ddb_set_categories(
+    state: &AppState,
+    sk: &str,
+    old: &[String],
+    new: &[String],
+) -> AResult<bool> {
+    let list = |c: &[String]| AttributeValue::L(c.iter().cloned().map(AttributeValue::S).collect());
+    let res = state
+        .dynamodb_client
+        .update_item()
+        .table_name(&state.table_name)
+        .set_key(Some(ddb_key(ARCHIVE_SK, sk)))
+        .update_expression("SET categories = :categories")
+        .condition_expression("attribute_exists(pk) AND categories = :old")
+        .expression_attribute_values(":categories", list(new))
+        .expression_attribute_values(":old", list(old))
+        .send()
+        .await;
+    match res {
+        Ok(_) => Ok(true),
+        Err(err)
+            if err
+                .as_service_error()
+                .is_some_and(|e| e.is_conditional_check_failed_exception()) =>
+        {
+            Ok(false)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+async fn ddb_get_entry(state: &AppState, sk: &str) -> AResult<Option<ArchiveEntry>> {
+    let res = state
+        .dynamodb_client
+        .get_item()
+        .table_name(&state.table_name)
+        .set_key(Some(ddb_key(ARCHIVE_SK, sk)))
+        .send()
+        .await?;
+    Ok(res.item.map(from_item).transpose()?)
+}
+
+impl CategoryCount {
+    /// Categories with at least one entry, alphabetically.
+    pub async fn ddb_list(state: &AppState) -> AResult<Vec<CategoryCount>> {
+        let items = ddb_query_partition(state, CATEGORY_CATALOG_PK).await?;
+        let catalog: Vec<CategoryCatalog> = from_items(items)?;
+        Ok(catalog
+            .into_iter()
+            .filter(|c| c.count > 0)
+            .map(|c| CategoryCount {
+                category: c.sk,
+                count: c.count,
+            })
+            .collect())
+    }
+}
+
+impl ArchiveEntry {
+    /// Moves the entry between category indexes after its categories changed.
+    pub async fn ddb_reindex_categories(
+        state: &AppState,
+        sk: &str,
+        old: &[String],
+        new: &[String],
+    ) -> AResult<()> {
+        let mut requests: Vec<WriteRequest> = Vec::new();
+        let mut deltas: HashMap<&str, i64> = HashMap::new();
+        for category in old.iter().filter(|c| !new.contains(c)) {
+            requests.push(delete_request(ddb_key(category_pk(category), sk))?);
+            deltas.insert(category, -1);
+        }
+        for category in new.iter().filter(|c| !old.contains(c)) {
+            let link = CategoryLink {
+                pk: category_pk(category),
+                sk: sk.to_string(),
+            };
+            requests.push(put_request(to_item(link)?)?);
+            deltas.insert(category, 1);
+        }
+
+        ddb_batch_write(state, requests).await?;
+        for (category, delta) in deltas {
+            ddb_add_count(state, category, delta).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn ddb_find_by_category(
+        state: &AppState,
+        category: &str,
+    ) -> AResult<Vec<ArchiveEntry>> {
+        let links: Vec<CategoryLink> =
+            from_items(ddb_query_partition(state, category_pk(category)).await?)?;
+        let keys = links
+            .into_iter()
+            .map(|l| ddb_key(ARCHIVE_SK, l.sk))
+            .collect();
+        let mut entries: Vec<ArchiveEntry> = from_items(ddb_batch_get(state, keys).await?)?;
+        entries.sort_by(|a, b| a.sk.cmp(&b.sk));
+        Ok(entries)
+    }
+
+    /// Renames a category on every entry in it. Renaming to an existing category merges
+    /// the two. Entries edited meanwhile are read again, and reported as conflicts when
+    /// they keep changing.
+    pub async fn ddb_rename_category(
+        state: &AppState,
+        rename: &CategoryRename,
+    ) -> AResult<CategoryRenameReport> {
+        let from = rename.from.trim();
+        let to = rename.to.trim();
+        if !rename.is_valid() {
+            return Err(anyhow::Error::msg(
+                "Categories must be non-empty and different from each other",
+            )
+            .into());
+        }
+
+        let entries = ArchiveEntry::ddb_find_by_category(state, from).await?;
+        let mut report = CategoryRenameReport {
+            renamed: 0,
+            conflicts: Vec::new(),
+        };
+        for entry in entries {
+            let sk = entry.sk.clone();
+            let mut current = Some(entry);
+            for attempt in 1..=RENAME_ATTEMPTS {
+                // deleted meanwhile, or no longer in the category
+                let Some(entry) = current else {
+                    break;
+                };
+                let old = entry.categories.unwrap_or_default();
+                let Some(new) = rename_in(&old, from, to) else {
+                    break;
+                };
+                if ddb_set_categories(state, &sk, &old, &new).await? {
+                    ArchiveEntry::ddb_reindex_categories(state, &sk, &old, &new).await?;
+                    report.renamed += 1;
+                    break;
+                }
+                if attempt == RENAME_ATTEMPTS {
+                    report.conflicts.push(sk.clone());
+                    break;
+                }
+                current = ddb_get_entry(state, &sk).await?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Drops the category indexes and builds them again from all archive entries.
+    /// Returns the number of indexed categories.
+    pub async fn ddb_rebuild_categories(state: &AppState) -> AResult<usize> {
+        let mut requests: Vec<WriteRequest> = Vec::new();
+        let catalog: Vec<CategoryCatalog> =
+            from_items(ddb_query_partition(state, CATEGORY_CATALOG_PK).await?)?;
+        for category in catalog {
+            let links: Vec<CategoryLink> =
+                from_items(ddb_query_partition(state, category_pk(&category.sk)).await?)?;
+            for link in links {
+                requests.push(delete_request(ddb_key(link.pk, link.sk))?);
+            }
+            requests.push(delete_request(ddb_key(category.pk, category.sk))?);
+        }
+        ddb_batch_write(state, requests).await?;
+
+        let entries: Vec<ArchiveEntry> = from_items(ddb_query_partition(state, ARCHIVE_SK).await?)?;
+        let mut counts: HashMap<String, i64> = HashMap::new();
+        let mut requests: Vec<WriteRequest> = Vec::new();
+        for entry in entries {
+            for category in normalize_categories(entry.categories).unwrap_or_default() {
+                let link = CategoryLink {
+                    pk: category_pk(&category),
+                    sk: entry.sk.clone(),
+                };
+                requests.push(put_request(to_item(link)?)?);
+                *counts.entry(category).or_default() += 1;
+            }
+        }
+        let indexed = counts.len();
+        for (category, count) in counts {
+            let catalog = CategoryCatalog {
+                pk: String::from(CATEGORY_CATALOG_PK),
+                sk: category,
+                count,
+            };
+            requests.push(put_request(to_item(catalog)?)?);
+        }
+        ddb_batch_write(state, requests).await?;
+        Ok(indexed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_normalize_categories() {
+        assert_eq!(
+            normalize_categories(Some(categories(&[" stoicism", "", "quotes", "stoicism "]))),
+            Some(categories(&["stoicism", "quotes"]))
+        );
+        assert_eq!(normalize_categories(Some(categories(&[" "]))), None);
+        assert_eq!(normalize_categories(None), None);
+    }
+
+    #[test]
+    fn test_rename_in() {
+        let old = categories(&["stoic", "quotes", "stoicism"]);
+        assert_eq!(
+            rename_in(&old, "quotes", "quote"),
+            Some(categories(&["stoic", "quote", "stoicism"]))
+        );
+        // merging into a category the entry already has
+        assert_eq!(
+            rename_in(&old, "stoic", "stoicism"),
+            Some(categories(&["stoicism", "quotes"]))
+        );
+        assert_eq!(rename_in(&old, "poems", "poetry"), None);
+    }
+}
@@ -0,0 +1,246 @@
+use std::collections::HashSet;
+
+use aws_sdk_dynamodb::types::{AttributeValue, Delete, Put, TransactWriteItem, WriteRequest};
+use serde::{Deserialize, Serialize};
+use serde_dynamo::{from_item, from_items, to_item};
+use sha2::{Digest, Sha256};
+
+use super::{ArchiveEntry, ARCHIVE_SK};
+use crate::utils::ddb::{
+    ddb_batch_get, ddb_batch_write, ddb_key, ddb_query_partition, delete_request, put_request,
+};
+use crate::{AResult, AppState};
+
+// one marker per archived quote, written in the same transaction as its entry, so the
+// same quote can't be archived twice even by concurrent requests
+const DEDUP_PK: &str = "ArchiveDedup";
+
+// a marker left by an entry deleted outside of the API is replaced this many times
+const DEDUP_ATTEMPTS: usize = 3;
+
+#[derive(Serialize, Deserialize)]
+struct DedupMarker {
+    pk: String,
+    sk: String, // SHA-256 of the dedup key, which can be longer than a sort key may be
+    entry_sk: String,
+}
+
+#[derive(Serialize)]
+pub struct DedupReport {
+    pub indexed: usize,
+    pub duplicates: Vec<String>, // sort keys of entries repeating an older entry
+}
+
+fn marker_sk(entry: &ArchiveEntry) -> String {
+    Sha256::digest(entry.dedup_key().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn marker_of(entry: &ArchiveEntry) -> DedupMarker {
+    DedupMarker {
+        pk: String::from(DEDUP_PK),
+        sk: marker_sk(entry),
+        entry_sk: entry.sk.clone(),
+    }
+}
+
+async fn ddb_get_marker(state: &AppState, entry: &ArchiveEntry) -> AResult<Option<DedupMarker>> {
+    let res = state
+        .dynamodb_client
+        .get_item()
+        .table_name(&state.table_name)
+        .set_key(Some(ddb_key(DEDUP_PK, marker_sk(entry))))
+        .send()
+        .await?;
+    Ok(res.item.map(from_item).transpose()?)
+}
+
+impl ArchiveEntry {
+    /// Put of the marker of the entry, failing the transaction when the quote is archived.
+    pub(super) fn dedup_marker_put(&self, state: &AppState) -> AResult<TransactWriteItem> {
+        let put = Put::builder()
+            .table_name(&state.table_name)
+            .set_item(Some(to_item(marker_of(self))?))
+            .condition_expression("attribute_not_exists(pk)")
+            .build()?;
+        Ok(TransactWriteItem::builder().put(put).build())
+    }
+
+    /// Delete of the marker of the entry, None when the marker belongs to another entry or
+    /// there is none, e.g. for entries archived before markers were written.
+    pub(super) async fn dedup_marker_delete(
+        &self,
+        state: &AppState,
+    ) -> AResult<Option<TransactWriteItem>> {
+        let Some(marker) = ddb_get_marker(state, self).await? else {
+            return Ok(None);
+        };
+        if marker.entry_sk != self.sk {
+            return Ok(None);
+        }
+        let delete = Delete::builder()
+            .table_name(&state.table_name)
+            .set_key(Some(ddb_key(marker.pk, marker.sk)))
+            .condition_expression("entry_sk = :sk")
+            .expression_attribute_values(":sk", AttributeValue::S(self.sk.clone()))
+            .build()?;
+        Ok(Some(TransactWriteItem::builder().delete(delete).build()))
+    }
+
+    /// The archived entry with the same quote, if there is one.
+    pub async fn ddb_find_duplicate(
+        state: &AppState,
+        entry: &ArchiveEntry,
+    ) -> AResult<Option<ArchiveEntry>> {
+        let Some(marker) = ddb_get_marker(state, entry).await? else {
+            return Ok(None);
+        };
+        if marker.entry_sk == entry.sk {
+            return Ok(None);
+        }
+        match ArchiveEntry::ddb_find(state, &marker.entry_sk).await {
+            Ok(existing) => Ok(Some(existing)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Dedup keys of the entries whose quotes are already archived.
+    pub async fn ddb_find_archived(
+        state: &AppState,
+        entries: &[ArchiveEntry],
+    ) -> AResult<HashSet<String>> {
+        let keys = entries
+            .iter()
+            .map(|e| ddb_key(DEDUP_PK, marker_sk(e)))
+            .collect();
+        let markers: Vec<DedupMarker> = from_items(ddb_batch_get(state, keys).await?)?;
+        let marked: HashSet<String> = markers.into_iter().map(|m| m.sk).collect();
+        Ok(entries
+            .iter()
+            .filter(|e| marked.contains(&marker_sk(e)))
+            .map(|e| e.dedup_key())
+            .collect())
+    }
+
+    /// Writes a new entry along with its marker. Returns the existing entry instead when
+    /// the quote is already archived.
+    pub async fn ddb_put_unique(
+        state: &AppState,
+        entry: &ArchiveEntry,
+    ) -> AResult<Option<ArchiveEntry>> {
+        let put = Put::builder()
+            .table_name(&state.table_name)
+            .set_item(Some(to_item(entry)?))
+            .condition_expression("attribute_not_exists(pk)")
+            .build()?;
+
+        for _ in 0..DEDUP_ATTEMPTS {
+            let res = state
+                .dynamodb_client
+                .transact_write_items()
+                .transact_items(TransactWriteItem::builder().put(put.clone()).build())
+                .transact_items(entry.dedup_marker_put(state)?)
+                .send()
+                .await;
+            match res {
+                Err(err)
+                    if err
+                        .as_service_error()
+                        .is_some_and(|e| e.is_transaction_canceled_exception()) => {}
+                Err(err) => return Err(err.into()),
+                Ok(_) => return Ok(None),
+            }
+
+            if let Some(existing) = ArchiveEntry::ddb_find_duplicate(state, entry).await? {
+                return Ok(Some(existing));
+            }
+            // the entry of the marker is gone, so the marker can be replaced
+            if let Some(marker) = ddb_get_marker(state, entry).await? {
+                let res = state
+                    .dynamodb_client
+                    .delete_item()
+                    .table_name(&state.table_name)
+                    .set_key(Some(ddb_key(marker.pk, marker.sk)))
+                    .condition_expression("entry_sk = :sk")
+                    .expression_attribute_values(":sk", AttributeValue::S(marker.entry_sk))
+                    .send()
+                    .await;
+                if let Err(err) = res {
+                    if !err
+                        .as_service_error()
+                        .is_some_and(|e| e.is_conditional_check_failed_exception())
+                    {
+                        return Err(err.into());
+                    }
+                }
+            }
+        }
+        Err(
+            anyhow::Error::msg("ArchiveEntry kept conflicting with another entry, try again")
+                .into(),
+        )
+    }
+
+    /// Drops the dedup markers and writes them again for all archive entries. The oldest
+    /// entry of a quote gets its marker, newer ones are reported as duplicates.
+    pub async fn ddb_rebuild_dedup_markers(state: &AppState) -> AResult<DedupReport> {
+        let requests = ddb_query_partition(state, DEDUP_PK)
+            .await?
+            .into_iter()
+            .map(|marker| -> AResult<WriteRequest> {
+                let marker: DedupMarker = from_item(marker)?;
+                delete_request(ddb_key(marker.pk, marker.sk))
+            })
+            .collect::<AResult<Vec<_>>>()?;
+        ddb_batch_write(state, requests).await?;
+
+        let mut entries: Vec<ArchiveEntry> =
+            from_items(ddb_query_partition(state, ARCHIVE_SK).await?)?;
+        entries.sort_by(|a, b| a.sk.cmp(&b.sk));
+        let mut marked: HashSet<String> = HashSet::new();
+        let mut duplicates = Vec::new();
+        let mut requests = Vec::new();
+        for entry in entries {
+            let marker = marker_of(&entry);
+            if !marked.insert(marker.sk.clone()) {
+                duplicates.push(entry.sk);
+                continue;
+            }
+            requests.push(put_request(to_item(marker)?)?);
+        }
+        ddb_batch_write(state, requests).await?;
+        Ok(DedupReport {
+            indexed: marked.len(),
+            duplicates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::{ArchiveEntryFC, ArchiveSource};
+
+    #[test]
+    fn test_marker_sk() {
+        let entry = |content: &str| {
+            ArchiveEntry::from(ArchiveEntryFC {
+                content: content.to_string(),
+                categories: None,
+                source: Some(ArchiveSource {
+                    author: Some(String::from("Seneca")),
+                    ..Default::default()
+                }),
+            })
+        };
+        let quote = entry("We suffer more often in imagination than in reality.");
+        let same = entry("we suffer more often  in imagination than in reality.");
+
+        assert_eq!(marker_sk(&quote).len(), 64);
+        assert_eq!(marker_sk(&quote), marker_sk(&same));
+        assert_ne!(marker_sk(&quote), marker_sk(&entry("Festina lente")));
+        assert_eq!(marker_of(&same).entry_sk, same.sk);
+    }
+}
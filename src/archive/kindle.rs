@@ -0,0 +1,356 @@
+use std::collections::HashSet;
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{ArchiveEntry, ArchiveEntryFC, ArchiveSource};
+use crate::search::SearchDoc;
+use crate::utils::id::id_at;
+use crate::utils::time::TIMEZONE;
+use crate::{AResult, AppState};
+
+const CLIPPING_SEPARATOR: &str = "==========";
+const PREVIEW_ENTRIES: usize = 20;
+
+// "Added on" dates of the US and the international Kindle locales
+const ADDED_FORMATS: [&str; 2] = ["%A, %B %d, %Y %I:%M:%S %p", "%A, %d %B %Y %H:%M:%S"];
+
+#[derive(Deserialize)]
+pub struct KindleImport {
+    pub clippings: String, // content of "My Clippings.txt"
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ClippingError {
+    pub clipping: usize, // position in the file, starting at 1
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct KindleImportReport {
+    pub dry_run: bool,
+    pub clippings: usize,
+    pub imported: usize,
+    pub duplicates: usize, // already archived or repeated in the file
+    pub skipped: usize,    // bookmarks and empty highlights
+    pub errors: Vec<ClippingError>,
+    pub preview: Vec<ArchiveEntry>, // first new entries as they will be stored
+}
+
+#[derive(PartialEq, Debug)]
+enum ClippingKind {
+    Highlight,
+    Note,
+    Bookmark,
+}
+
+#[derive(Debug)]
+struct Clipping {
+    title: String,
+    author: Option<String>,
+    kind: ClippingKind,
+    location: Option<String>, // e.g. "p. 42, loc. 1234-1240"
+    added: Option<NaiveDateTime>,
+    text: String,
+}
+
+/// Splits "Meditations (Marcus Aurelius)" into the title and the author in the last
+/// parentheses.
+fn parse_title_line(line: &str) -> (String, Option<String>) {
+    let line = line.trim_start_matches('\u{feff}').trim();
+    if let Some(rest) = line.strip_suffix(')') {
+        if let Some(open) = rest.rfind('(') {
+            let title = rest[..open].trim();
+            let author = rest[open + 1..].trim();
+            if !title.is_empty() && !author.is_empty() {
+                return (title.to_string(), Some(author.to_string()));
+            }
+        }
+    }
+    (line.to_string(), None)
+}
+
+/// Parses e.g. "- Your Highlight on page 42 | Location 1234-1240 | Added on Monday, 1 May 2024
+/// 08:30:00" into the kind, the location and the date.
+fn parse_meta_line(
+    line: &str,
+) -> Result<(ClippingKind, Option<String>, Option<NaiveDateTime>), String> {
+    let Some(meta) = line.trim().strip_prefix("- Your ") else {
+        return Err(format!("Unexpected clipping details '{}'", line.trim()));
+    };
+    let kind = match meta.split_whitespace().next() {
+        Some("Highlight") => ClippingKind::Highlight,
+        Some("Note") => ClippingKind::Note,
+        Some("Bookmark") => ClippingKind::Bookmark,
+        _ => return Err(format!("Unknown clipping type in '{}'", line.trim())),
+    };
+
+    let (mut page, mut location, mut added) = (None, None, None);
+    for part in meta.split(" | ") {
+        let lowercase = part.to_lowercase();
+        if let Some(date) = part.trim().strip_prefix("Added on ") {
+            added = ADDED_FORMATS
+                .iter()
+                .find_map(|f| NaiveDateTime::parse_from_str(date.trim(), f).ok());
+        } else if let Some(i) = lowercase.find("page ") {
+            page = part[i + "page ".len()..].split_whitespace().next();
+        } else if let Some(i) = lowercase.find("location ") {
+            location = part[i + "location ".len()..].split_whitespace().next();
+        }
+    }
+    let location = match (page, location) {
+        (Some(page), Some(location)) => Some(format!("p. {}, loc. {}", page, location)),
+        (Some(page), None) => Some(format!("p. {}", page)),
+        (None, Some(location)) => Some(format!("loc. {}", location)),
+        (None, None) => None,
+    };
+    Ok((kind, location, added))
+}
+
+/// Parses clippings in file order. Malformed ones are reported by their position.
+fn parse_clippings(clippings: &str) -> (Vec<Clipping>, Vec<ClippingError>) {
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+    let blocks = clippings
+        .split(CLIPPING_SEPARATOR)
+        .map(|b| b.trim_matches(|c: char| c.is_whitespace() || c == '\u{feff}'))
+        .filter(|b| !b.is_empty());
+
+    for (i, block) in blocks.enumerate() {
+        let clipping = i + 1;
+        let mut lines = block.lines();
+        let (Some(title_line), Some(meta_line)) = (lines.next(), lines.next()) else {
+            errors.push(ClippingError {
+                clipping,
+                message: String::from("Clipping has no title or details line"),
+            });
+            continue;
+        };
+        let (kind, location, added) = match parse_meta_line(meta_line) {
+            Ok(meta) => meta,
+            Err(message) => {
+                errors.push(ClippingError { clipping, message });
+                continue;
+            }
+        };
+        let (title, author) = parse_title_line(title_line);
+        let text = lines.map(str::trim).collect::<Vec<&str>>().join("\n");
+        parsed.push(Clipping {
+            title,
+            author,
+            kind,
+            location,
+            added,
+            text: text.trim().to_string(),
+        });
+    }
+    (parsed, errors)
+}
+
+impl Clipping {
+    fn into_entry(self) -> ArchiveEntry {
+        let mut entry = ArchiveEntry::from(ArchiveEntryFC {
+            content: self.text,
+            categories: Some(vec![self.title.clone()]),
+            source: Some(ArchiveSource {
+                author: self.author,
+                title: Some(self.title),
+                location: self.location,
+                added_from: Some(String::from("kindle")),
+                ..Default::default()
+            }),
+        });
+        // keep the archive in the order things were highlighted
+        if let Some(added) = self
+            .added
+            .and_then(|a| TIMEZONE.from_local_datetime(&a).earliest())
+        {
+            entry.sk = id_at(added.with_timezone(&Utc));
+        }
+        entry
+    }
+}
+
+/// Entries of highlights and notes that aren't in `known` yet, along with the number of
+/// duplicates and of skipped bookmarks and empty clippings. `known` gets the new keys.
+fn new_entries(
+    clippings: Vec<Clipping>,
+    known: &mut HashSet<String>,
+) -> (Vec<ArchiveEntry>, usize, usize) {
+    let (mut entries, mut duplicates, mut skipped) = (Vec::new(), 0, 0);
+    for clipping in clippings {
+        if clipping.kind == ClippingKind::Bookmark || clipping.text.is_empty() {
+            skipped += 1;
+            continue;
+        }
+        let entry = clipping.into_entry();
+        if !known.insert(entry.dedup_key()) {
+            duplicates += 1;
+            continue;
+        }
+        entries.push(entry);
+    }
+    (entries, duplicates, skipped)
+}
+
+impl KindleImportReport {
+    pub async fn ddb_import(state: &AppState, import: KindleImport) -> AResult<KindleImportReport> {
+        let (clippings, errors) = parse_clippings(&import.clippings);
+        let total = clippings.len() + errors.len();
+
+        let (entries, mut duplicates, skipped) = new_entries(clippings, &mut HashSet::new());
+        let archived = ArchiveEntry::ddb_find_archived(state, &entries).await?;
+        let (mut entries, repeated): (Vec<ArchiveEntry>, Vec<ArchiveEntry>) = entries
+            .into_iter()
+            .partition(|e| !archived.contains(&e.dedup_key()));
+        duplicates += repeated.len();
+
+        let mut imported = 0;
+        if !import.dry_run {
+            let mut written = Vec::new();
+            for entry in entries {
+                // archived meanwhile by another request
+                if ArchiveEntry::ddb_put_unique(state, &entry).await?.is_some() {
+                    duplicates += 1;
+                    continue;
+                }
+                let categories = entry.categories.clone().unwrap_or_default();
+                ArchiveEntry::ddb_reindex_categories(state, &entry.sk, &[], &categories).await?;
+                SearchDoc::ddb_reindex(state, &entry.pk, &entry.sk, Some(&entry.content)).await?;
+                written.push(entry);
+            }
+            imported = written.len();
+            entries = written;
+        }
+
+        entries.truncate(PREVIEW_ENTRIES);
+        Ok(KindleImportReport {
+            dry_run: import.dry_run,
+            clippings: total,
+            imported,
+            duplicates,
+            skipped,
+            errors,
+            preview: entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIPPINGS: &str = "\u{feff}Meditations (Marcus Aurelius)\r
+- Your Highlight on page 42 | Location 610-612 | Added on Wednesday, 1 May 2024 08:30:00\r
+\r
+You have power over your mind - not outside events.\r
+==========\r
+Letters from a Stoic (Seneca)
+- Your Highlight at location 1234-1240 | Added on Thursday, May 2, 2024 9:05:10 PM
+
+We suffer more often in imagination than in reality.
+==========
+Letters from a Stoic (Seneca)
+- Your Bookmark at location 1300 | Added on Thursday, May 2, 2024 9:06:00 PM
+
+
+==========
+Notes
+- Your Note at location 12 | Added on someday
+
+Read again
+==========
+Broken clipping
+==========
+Letters from a Stoic (Seneca)
+- Your Highlight at location 1234-1240 | Added on Friday, May 3, 2024 7:00:00 AM
+
+We suffer more often  in imagination than in reality.
+==========
+";
+
+    #[test]
+    fn test_parse_title_line() {
+        assert_eq!(
+            parse_title_line("The Stoic Challenge (Irvine, William B.)"),
+            (
+                String::from("The Stoic Challenge"),
+                Some(String::from("Irvine, William B."))
+            )
+        );
+        assert_eq!(
+            parse_title_line("Notes (draft) (Jane Doe)"),
+            (
+                String::from("Notes (draft)"),
+                Some(String::from("Jane Doe"))
+            )
+        );
+        assert_eq!(parse_title_line("Notes"), (String::from("Notes"), None));
+    }
+
+    #[test]
+    fn test_parse_clippings() {
+        let (clippings, errors) = parse_clippings(CLIPPINGS);
+        assert_eq!(clippings.len(), 5);
+        assert_eq!(
+            errors,
+            vec![ClippingError {
+                clipping: 5,
+                message: String::from("Clipping has no title or details line"),
+            }]
+        );
+
+        let meditations = &clippings[0];
+        assert_eq!(meditations.title, "Meditations");
+        assert_eq!(meditations.author.as_deref(), Some("Marcus Aurelius"));
+        assert_eq!(meditations.location.as_deref(), Some("p. 42, loc. 610-612"));
+        assert_eq!(
+            meditations.added.unwrap().to_string(),
+            "2024-05-01 08:30:00"
+        );
+        assert_eq!(
+            meditations.text,
+            "You have power over your mind - not outside events."
+        );
+
+        let letters = &clippings[1];
+        assert_eq!(letters.location.as_deref(), Some("loc. 1234-1240"));
+        assert_eq!(letters.added.unwrap().to_string(), "2024-05-02 21:05:10");
+        assert_eq!(clippings[2].kind, ClippingKind::Bookmark);
+
+        let note = &clippings[3];
+        assert_eq!(note.kind, ClippingKind::Note);
+        assert_eq!(note.author, None);
+        assert_eq!(note.added, None);
+    }
+
+    #[test]
+    fn test_new_entries() {
+        let (clippings, _) = parse_clippings(CLIPPINGS);
+        let archived = Clipping {
+            title: String::from("Meditations"),
+            author: Some(String::from("Marcus Aurelius")),
+            kind: ClippingKind::Highlight,
+            location: None,
+            added: None,
+            text: String::from("You have power over your mind - not outside events."),
+        };
+        let mut known = HashSet::from([archived.into_entry().dedup_key()]);
+
+        let (entries, duplicates, skipped) = new_entries(clippings, &mut known);
+        assert_eq!((entries.len(), duplicates, skipped), (2, 2, 1));
+
+        let letters = &entries[0];
+        assert!(letters.sk.starts_with("2024-05-02T21:05:10+02:00_"));
+        assert_eq!(
+            letters.categories,
+            Some(vec![String::from("Letters from a Stoic")])
+        );
+        let source = letters.source.as_ref().unwrap();
+        assert_eq!(source.author.as_deref(), Some("Seneca"));
+        assert_eq!(source.added_from.as_deref(), Some("kindle"));
+        assert_eq!(entries[1].content, "Read again");
+    }
+}
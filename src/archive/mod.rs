@@ -1,8 +1,23 @@
+mod anki;
+mod categories;
+mod dedup;
+mod kindle;
 mod model;
+mod random;
+mod reads;
+mod review;
 mod routes;
 
-pub use model::ArchiveEntry;
-pub use model::ArchiveEntryFC;
+pub use anki::{to_anki_tsv, AnkiExportParams};
+pub use categories::{CategoryCount, CategoryRename, CategoryRenameReport};
+pub use dedup::DedupReport;
+pub use kindle::{KindleImport, KindleImportReport};
+pub use model::{
+    ArchiveCreate, ArchiveEntry, ArchiveEntryFC, ArchiveEntryFU, ArchiveSource, ArchiveUpdate,
+};
+pub use random::RandomParams;
+pub use reads::{ArchiveRead, ReadHistory};
+pub use review::{ReviewFC, ReviewOutcome, MAX_GRADE};
 pub use routes::router;
 
 pub const ARCHIVE_SK: &str = "Archive::Entry";
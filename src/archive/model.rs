@@ -1,11 +1,19 @@
-use crate::{utils::time::get_today_datetime, AResult, AppState};
-use aws_sdk_dynamodb::types::AttributeValue;
+use crate::attachment::Attachment;
+use crate::search::SearchDoc;
+use crate::utils::ddb::ddb_key;
+use crate::utils::id::new_id;
+use crate::utils::time::get_date_x_days_ago;
+use crate::{AResult, AppState};
+use aws_sdk_dynamodb::types::{AttributeValue, Delete, TransactWriteItem, Update};
 use serde::{Deserialize, Serialize};
 use serde_dynamo::{from_item, from_items, to_item};
 
+use super::categories::normalize_categories;
+use super::reads::ArchiveRead;
+use super::review::{default_ease, DEFAULT_EASE};
 use super::ARCHIVE_SK;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct ArchiveEntry {
     pub pk: String,
     pub sk: String,
@@ -13,62 +21,311 @@ pub struct ArchiveEntry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub categories: Option<Vec<String>>,
     pub read_times: u64,
+    // review schedule, see review.rs
+    #[serde(default = "default_ease")]
+    pub ease: f64,
+    #[serde(default)]
+    pub interval: u32, // days until the next review
+    #[serde(default)]
+    pub repetitions: u32, // passing reviews in a row
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>, // e.g. "2024-05-01"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_read: Option<String>, // sort key of the latest ArchiveRead
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<ArchiveSource>,
+}
+
+/// Where a quote or highlight comes from.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, PartialEq)]
+pub struct ArchiveSource {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>, // page or e-reader location, e.g. "p. 42" or "1234-1240"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added_from: Option<String>, // e.g. "kindle", "web"
 }
 
 #[derive(Deserialize)]
 pub struct ArchiveEntryFC {
     pub content: String,
     pub categories: Option<Vec<String>>,
+    pub source: Option<ArchiveSource>,
+}
+
+/// Fields to change, missing ones are kept. An empty `categories` list or a `source`
+/// without any field removes them.
+#[derive(Deserialize, Default)]
+pub struct ArchiveEntryFU {
+    pub content: Option<String>,
+    pub categories: Option<Vec<String>>,
+    pub source: Option<ArchiveSource>,
+}
+
+pub enum ArchiveCreate {
+    Created(ArchiveEntry),
+    Duplicate(ArchiveEntry), // the existing entry
+}
+
+pub enum ArchiveUpdate {
+    Updated(Box<ArchiveEntry>),
+    NotFound,
+    Conflict,                     // categories or the quote changed since the entry was read
+    Duplicate(Box<ArchiveEntry>), // the entry already archiving the edited quote
+}
+
+fn normalize_field(value: Option<String>) -> Option<String> {
+    value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+impl ArchiveSource {
+    /// Trims fields and drops empty ones, None when nothing is left.
+    pub fn normalize(self) -> Option<ArchiveSource> {
+        let source = ArchiveSource {
+            author: normalize_field(self.author),
+            title: normalize_field(self.title),
+            url: normalize_field(self.url),
+            location: normalize_field(self.location),
+            added_from: normalize_field(self.added_from),
+        };
+        match source == ArchiveSource::default() {
+            true => None,
+            false => Some(source),
+        }
+    }
 }
 
 impl From<ArchiveEntryFC> for ArchiveEntry {
     fn from(fc: ArchiveEntryFC) -> Self {
         let pk = String::from(ARCHIVE_SK);
-        let sk = get_today_datetime();
+        let sk = new_id();
         ArchiveEntry {
             pk,
             sk,
-            content: fc.content,
-            categories: fc.categories,
+            content: fc.content.trim().to_string(),
+            categories: normalize_categories(fc.categories),
             read_times: 0,
+            ease: DEFAULT_EASE,
+            interval: 0,
+            repetitions: 0,
+            due: Some(get_date_x_days_ago(0)),
+            last_read: None,
+            source: fc.source.and_then(ArchiveSource::normalize),
         }
     }
 }
 
 impl ArchiveEntry {
-    pub async fn ddb_increment_read_times(state: &AppState, sk: impl Into<String>) -> AResult<()> {
-        let mut found_arch = match ArchiveEntry::ddb_find(state, sk).await {
-            Ok(res) => res,
-            Err(_) => {
-                return Err(anyhow::Error::msg(
-                    "ArchiveEntry with provided sort key does not exist",
-                )
-                .into())
-            }
+    /// Entries with the same key are the same quote: content compared regardless of case
+    /// and whitespace, from the same author and title.
+    pub fn dedup_key(&self) -> String {
+        let normalize = |text: &str| {
+            text.split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ")
+                .to_lowercase()
         };
-        found_arch.read_times += 1;
-        let item = to_item(found_arch)?;
-        state
-            .dynamodb_client
-            .put_item()
-            .table_name(&state.table_name)
-            .set_item(Some(item))
-            .send()
-            .await?;
+        let source = self.source.clone().unwrap_or_default();
+        format!(
+            "{}|{}|{}",
+            normalize(&self.content),
+            normalize(source.author.as_deref().unwrap_or_default()),
+            normalize(source.title.as_deref().unwrap_or_default())
+        )
+    }
+
+    /// Applies changes of a PATCH. Content can't be emptied.
+    pub fn apply(&mut self, fu: ArchiveEntryFU) -> AResult<()> {
+        if let Some(content) = fu.content {
+            let content = content.trim();
+            if content.is_empty() {
+                return Err(anyhow::Error::msg("Content cannot be empty").into());
+            }
+            self.content = content.to_string();
+        }
+        if let Some(categories) = fu.categories {
+            self.categories = normalize_categories(Some(categories));
+        }
+        if let Some(source) = fu.source {
+            self.source = source.normalize();
+        }
         Ok(())
     }
 
-    pub async fn ddb_create(state: &AppState, record_fc: ArchiveEntryFC) -> AResult<()> {
-        let item = to_item(Into::<ArchiveEntry>::into(record_fc))?;
+    pub async fn ddb_create(state: &AppState, record_fc: ArchiveEntryFC) -> AResult<ArchiveCreate> {
+        let arch_entry: ArchiveEntry = record_fc.into();
+        if arch_entry.content.is_empty() {
+            return Err(anyhow::Error::msg("Content cannot be empty").into());
+        }
+        if let Some(existing) = ArchiveEntry::ddb_put_unique(state, &arch_entry).await? {
+            return Ok(ArchiveCreate::Duplicate(existing));
+        }
 
-        let req = state
-            .dynamodb_client
-            .put_item()
+        let categories = arch_entry.categories.clone().unwrap_or_default();
+        ArchiveEntry::ddb_reindex_categories(state, &arch_entry.sk, &[], &categories).await?;
+        SearchDoc::ddb_reindex(
+            state,
+            &arch_entry.pk,
+            &arch_entry.sk,
+            Some(&arch_entry.content),
+        )
+        .await?;
+        Ok(ArchiveCreate::Created(arch_entry))
+    }
+
+    /// Saves only the attributes present in the PATCH, so reads, reviews and other edits
+    /// saved meanwhile are kept. Categories are replaced only if they haven't changed since
+    /// the entry was read, e.g. by a category rename. Editing the quote moves its dedup
+    /// marker in the same transaction.
+    pub async fn ddb_update(
+        state: &AppState,
+        sk: impl Into<String>,
+        fu: ArchiveEntryFU,
+    ) -> AResult<ArchiveUpdate> {
+        let Ok(mut entry) = ArchiveEntry::ddb_find(state, sk).await else {
+            return Ok(ArchiveUpdate::NotFound);
+        };
+        let old_entry = entry.clone();
+        let old_content = entry.content.clone();
+        let old_categories = entry.categories.clone();
+        let (content, categories, source) = (
+            fu.content.is_some(),
+            fu.categories.is_some(),
+            fu.source.is_some(),
+        );
+        entry.apply(fu)?;
+        if !content && !categories && !source {
+            return Ok(ArchiveUpdate::Updated(Box::new(entry)));
+        }
+
+        let mut set = Vec::new();
+        let mut remove = Vec::new();
+        let mut conditions = vec!["attribute_exists(pk)"];
+        let mut update = Update::builder()
             .table_name(&state.table_name)
-            .set_item(Some(item));
+            .set_key(Some(ddb_key(&entry.pk, &entry.sk)));
+        if content {
+            set.push("#content = :content");
+            update = update
+                .expression_attribute_names("#content", "content")
+                .expression_attribute_values(":content", AttributeValue::S(entry.content.clone()));
+        }
+        if categories {
+            let list = |c: &[String]| {
+                AttributeValue::L(c.iter().cloned().map(AttributeValue::S).collect())
+            };
+            match &entry.categories {
+                Some(new) => {
+                    set.push("categories = :categories");
+                    update = update.expression_attribute_values(":categories", list(new));
+                }
+                None => remove.push("categories"),
+            }
+            match &old_categories {
+                Some(old) => {
+                    conditions.push("categories = :old_categories");
+                    update = update.expression_attribute_values(":old_categories", list(old));
+                }
+                None => conditions.push("attribute_not_exists(categories)"),
+            }
+        }
+        if source {
+            update = update.expression_attribute_names("#source", "source");
+            match &entry.source {
+                Some(source) => {
+                    set.push("#source = :source");
+                    update = update.expression_attribute_values(
+                        ":source",
+                        AttributeValue::M(to_item(source)?),
+                    );
+                }
+                None => remove.push("#source"),
+            }
+        }
+        let mut markers = Vec::new();
+        if entry.dedup_key() != old_entry.dedup_key() {
+            // the old marker is only dropped if the quote wasn't edited meanwhile
+            conditions.push("#content = :old_content");
+            update = update
+                .expression_attribute_names("#content", "content")
+                .expression_attribute_values(
+                    ":old_content",
+                    AttributeValue::S(old_content.clone()),
+                );
+            match &old_entry.source {
+                Some(old) => {
+                    conditions.push("#source = :old_source");
+                    update = update
+                        .expression_attribute_names("#source", "source")
+                        .expression_attribute_values(
+                            ":old_source",
+                            AttributeValue::M(to_item(old)?),
+                        );
+                }
+                None => {
+                    conditions.push("attribute_not_exists(#source)");
+                    update = update.expression_attribute_names("#source", "source");
+                }
+            }
+            markers.extend(old_entry.dedup_marker_delete(state).await?);
+            markers.push(entry.dedup_marker_put(state)?);
+        }
+        let mut expression = Vec::new();
+        if !set.is_empty() {
+            expression.push(format!("SET {}", set.join(", ")));
+        }
+        if !remove.is_empty() {
+            expression.push(format!("REMOVE {}", remove.join(", ")));
+        }
+        let update = update
+            .update_expression(expression.join(" "))
+            .condition_expression(conditions.join(" AND "))
+            .build()?;
 
-        req.send().await?;
-        Ok(())
+        let mut actions = vec![TransactWriteItem::builder().update(update).build()];
+        actions.extend(markers);
+
+        let res = state
+            .dynamodb_client
+            .transact_write_items()
+            .set_transact_items(Some(actions))
+            .send()
+            .await;
+        match res {
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|e| e.is_transaction_canceled_exception()) =>
+            {
+                return match ArchiveEntry::ddb_find_duplicate(state, &entry).await? {
+                    Some(existing) => Ok(ArchiveUpdate::Duplicate(Box::new(existing))),
+                    None => Ok(ArchiveUpdate::Conflict),
+                };
+            }
+            Err(err) => return Err(err.into()),
+            Ok(_) => {}
+        }
+
+        if categories {
+            ArchiveEntry::ddb_reindex_categories(
+                state,
+                &entry.sk,
+                old_categories.as_deref().unwrap_or_default(),
+                entry.categories.as_deref().unwrap_or_default(),
+            )
+            .await?;
+        }
+        if entry.content != old_content {
+            SearchDoc::ddb_reindex(state, &entry.pk, &entry.sk, Some(&entry.content)).await?;
+        }
+        Ok(ArchiveUpdate::Updated(Box::new(entry)))
     }
 
     pub async fn ddb_find_all(state: AppState) -> AResult<Vec<ArchiveEntry>> {
@@ -83,11 +340,9 @@ impl ArchiveEntry {
         match res.items {
             Some(items) => {
                 let arch_entries: Vec<ArchiveEntry> = from_items(items)?;
-                return Ok(arch_entries);
-            }
-            None => {
-                return Err(anyhow::Error::msg("Error querying DynamoDB Records. {:?}").into());
+                Ok(arch_entries)
             }
+            None => Err(anyhow::Error::msg("Error querying DynamoDB Records. {:?}").into()),
         }
     }
 
@@ -109,14 +364,104 @@ impl ArchiveEntry {
     }
 
     pub async fn ddb_delete(state: &AppState, sk: impl Into<String>) -> AResult<()> {
+        let sk = sk.into();
+        let delete = Delete::builder()
+            .table_name(&state.table_name)
+            .set_key(Some(ddb_key(ARCHIVE_SK, &sk)))
+            .build()?;
+        let mut actions = vec![TransactWriteItem::builder().delete(delete).build()];
+        let categories = match ArchiveEntry::ddb_find(state, &sk).await {
+            Ok(entry) => {
+                actions.extend(entry.dedup_marker_delete(state).await?);
+                entry.categories.unwrap_or_default()
+            }
+            Err(_) => Vec::new(),
+        };
         state
             .dynamodb_client
-            .delete_item()
-            .table_name(&state.table_name)
-            .key("pk", AttributeValue::S(String::from(ARCHIVE_SK)))
-            .key("sk", AttributeValue::S(sk.into()))
+            .transact_write_items()
+            .set_transact_items(Some(actions))
             .send()
             .await?;
+
+        SearchDoc::ddb_unindex(state, ARCHIVE_SK, &sk).await?;
+        Attachment::ddb_delete_all(state, ARCHIVE_SK, &sk).await?;
+        ArchiveRead::ddb_delete_all(state, &sk).await?;
+        ArchiveEntry::ddb_reindex_categories(state, &sk, &categories, &[]).await?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(content: &str, source: Option<ArchiveSource>) -> ArchiveEntry {
+        ArchiveEntry::from(ArchiveEntryFC {
+            content: content.to_string(),
+            categories: None,
+            source,
+        })
+    }
+
+    fn source(author: &str, title: &str) -> Option<ArchiveSource> {
+        Some(ArchiveSource {
+            author: Some(author.to_string()),
+            title: Some(title.to_string()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_source_normalize() {
+        let blank = ArchiveSource {
+            author: Some(String::from("  ")),
+            url: Some(String::new()),
+            ..Default::default()
+        };
+        assert_eq!(blank.normalize(), None);
+        assert_eq!(
+            source(" Seneca ", "Letters").unwrap().normalize(),
+            source("Seneca", "Letters")
+        );
+    }
+
+    #[test]
+    fn test_dedup_key() {
+        let quote = entry(
+            "We suffer more  in imagination",
+            source("Seneca", "Letters"),
+        );
+        let same = entry(
+            "we suffer more in\nimagination ",
+            source("seneca", "Letters"),
+        );
+        let other_book = entry(
+            "We suffer more in imagination",
+            source("Seneca", "On Anger"),
+        );
+        assert_eq!(quote.dedup_key(), same.dedup_key());
+        assert_ne!(quote.dedup_key(), other_book.dedup_key());
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut quote = entry("Festina lente", source("Augustus", ""));
+        quote
+            .apply(ArchiveEntryFU {
+                categories: Some(vec![String::from(" latin ")]),
+                source: Some(ArchiveSource::default()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(quote.content, "Festina lente");
+        assert_eq!(quote.categories, Some(vec![String::from("latin")]));
+        assert_eq!(quote.source, None);
+
+        let empty = ArchiveEntryFU {
+            content: Some(String::from(" ")),
+            ..Default::default()
+        };
+        assert!(quote.apply(empty).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+use chrono::{DateTime, Utc};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+use serde_dynamo::from_items;
+
+use super::{ArchiveEntry, ARCHIVE_SK};
+use crate::utils::ddb::ddb_query_partition;
+use crate::utils::id::id_timestamp;
+use crate::{AResult, AppState};
+
+const DEFAULT_RANDOM_ITEMS: usize = 1;
+// marking entries as read takes two of the 100 actions of a transaction per entry
+const MAX_RANDOM_ITEMS: usize = 50;
+// entries unread for longer than this are all equally overdue
+const MAX_UNREAD_DAYS: i64 = 365;
+
+#[derive(Deserialize)]
+pub struct RandomParams {
+    pub n: Option<usize>,
+    pub category: Option<String>,
+    #[serde(default)]
+    pub read: bool, // count the returned entries as read
+}
+
+/// Weight of an entry in random picks: grows with days since it was last read (or created,
+/// if never read) and shrinks with the number of reads.
+pub fn read_weight(entry: &ArchiveEntry, now: DateTime<Utc>) -> f64 {
+    let last_seen = entry
+        .last_read
+        .as_deref()
+        .unwrap_or(id_timestamp(&entry.sk));
+    let days = DateTime::parse_from_rfc3339(last_seen)
+        .map(|seen| (now - seen.with_timezone(&Utc)).num_days())
+        .unwrap_or(MAX_UNREAD_DAYS)
+        .clamp(0, MAX_UNREAD_DAYS);
+    (1 + days) as f64 / (1 + entry.read_times) as f64
+}
+
+/// Picks up to `n` distinct entries at random, proportionally to their `read_weight`.
+pub fn pick_weighted<R: Rng>(
+    entries: Vec<ArchiveEntry>,
+    n: usize,
+    now: DateTime<Utc>,
+    rng: &mut R,
+) -> AResult<Vec<ArchiveEntry>> {
+    let weights: Vec<f64> = entries.iter().map(|e| read_weight(e, now)).collect();
+    let indexes: Vec<usize> = (0..entries.len()).collect();
+    let mut picked: Vec<usize> = indexes
+        .choose_multiple_weighted(rng, n, |&i| weights[i])
+        .map_err(anyhow::Error::msg)?
+        .copied()
+        .collect();
+    picked.sort_unstable();
+
+    Ok(entries
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| picked.binary_search(i).is_ok())
+        .map(|(_, e)| e)
+        .collect())
+}
+
+impl ArchiveEntry {
+    pub async fn ddb_random(state: &AppState, params: RandomParams) -> AResult<Vec<ArchiveEntry>> {
+        let n = params
+            .n
+            .unwrap_or(DEFAULT_RANDOM_ITEMS)
+            .clamp(1, MAX_RANDOM_ITEMS);
+        let entries: Vec<ArchiveEntry> = match &params.category {
+            Some(category) => ArchiveEntry::ddb_find_by_category(state, category.trim()).await?,
+            None => from_items(ddb_query_partition(state, ARCHIVE_SK).await?)?,
+        };
+        let picked = pick_weighted(entries, n, Utc::now(), &mut rand::thread_rng())?;
+        if !params.read {
+            return Ok(picked);
+        }
+
+        ArchiveEntry::ddb_mark_read_all(state, picked).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn entry(sk: &str, read_times: u64, last_read: Option<&str>) -> ArchiveEntry {
+        ArchiveEntry {
+            pk: String::from(ARCHIVE_SK),
+            sk: String::from(sk),
+            content: String::from("Festina lente"),
+            categories: None,
+            read_times,
+            ease: 2.5,
+            interval: 0,
+            repetitions: 0,
+            due: None,
+            last_read: last_read.map(String::from),
+            source: None,
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-05-11T12:00:00+02:00")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_read_weight() {
+        // created ten days ago, never read
+        assert_eq!(
+            read_weight(&entry("2024-05-01T08:00:00+02:00", 0, None), now()),
+            11.0
+        );
+        assert_eq!(
+            read_weight(
+                &entry(
+                    "2020-01-01T08:00:00+01:00",
+                    3,
+                    Some("2024-05-10T08:00:00+02:00")
+                ),
+                now()
+            ),
+            0.5
+        );
+        assert_eq!(
+            read_weight(&entry("2010-01-01T08:00:00+01:00", 0, None), now()),
+            366.0
+        );
+        // read "in the future" because of clock skew
+        assert_eq!(
+            read_weight(
+                &entry(
+                    "2020-01-01T08:00:00+01:00",
+                    0,
+                    Some("2024-05-12T08:00:00+02:00")
+                ),
+                now()
+            ),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_pick_weighted() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let entries = || {
+            vec![
+                entry(
+                    "2024-05-10T08:00:00+02:00",
+                    9,
+                    Some("2024-05-11T08:00:00+02:00"),
+                ),
+                entry("2023-05-10T08:00:00+02:00", 0, None),
+                entry(
+                    "2024-01-10T08:00:00+02:00",
+                    1,
+                    Some("2024-03-01T08:00:00+02:00"),
+                ),
+            ]
+        };
+
+        let picked = pick_weighted(entries(), 2, now(), &mut rng).unwrap();
+        assert_eq!(picked.len(), 2);
+        assert_ne!(picked[0].sk, picked[1].sk);
+
+        let all = pick_weighted(entries(), 5, now(), &mut rng).unwrap();
+        assert_eq!(all.len(), 3);
+
+        // the long unread entry is far more likely than the one read an hour ago
+        let mut counts = [0; 3];
+        for _ in 0..1000 {
+            let picked = pick_weighted(entries(), 1, now(), &mut rng).unwrap();
+            let i = entries().iter().position(|e| e.sk == picked[0].sk).unwrap();
+            counts[i] += 1;
+        }
+        assert!(counts[1] > counts[2] && counts[2] > counts[0]);
+
+        assert!(pick_weighted(Vec::new(), 1, now(), &mut rng)
+            .unwrap()
+            .is_empty());
+    }
+}
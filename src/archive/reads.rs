@@ -0,0 +1,147 @@
+use aws_sdk_dynamodb::types::{AttributeValue, Put, TransactWriteItem, Update};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_dynamo::{from_items, to_item};
+
+use super::ArchiveEntry;
+use crate::utils::ddb::{
+    ddb_batch_write, ddb_key, ddb_query_partition, delete_request, TRANSACT_WRITE_LIMIT,
+};
+use crate::utils::time::TIMEZONE;
+use crate::{AResult, AppState};
+
+pub fn archive_read_pk(entry_sk: &str) -> String {
+    format!("ArchiveRead::{}", entry_sk)
+}
+
+/// A single read of an ArchiveEntry, kept in a partition per entry.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ArchiveRead {
+    pub pk: String,
+    pub sk: String, // time of the read with microseconds, so quick reads don't collide
+    pub entry_sk: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grade: Option<u8>, // recall grade, reads outside of reviews have none
+}
+
+#[derive(Serialize)]
+pub struct ReadHistory {
+    pub sk: String,
+    pub read_times: u64, // includes reads from before events were recorded
+    pub last_read: Option<String>,
+    pub reads: Vec<ArchiveRead>, // newest first
+}
+
+impl ArchiveRead {
+    pub fn new(entry_sk: &str, grade: Option<u8>) -> ArchiveRead {
+        ArchiveRead {
+            pk: archive_read_pk(entry_sk),
+            sk: Utc::now()
+                .with_timezone(&TIMEZONE)
+                .to_rfc3339_opts(SecondsFormat::Micros, true),
+            entry_sk: entry_sk.to_string(),
+            grade,
+        }
+    }
+
+    pub async fn ddb_list(state: &AppState, entry_sk: &str) -> AResult<Vec<ArchiveRead>> {
+        let items = ddb_query_partition(state, archive_read_pk(entry_sk)).await?;
+        Ok(from_items(items)?)
+    }
+
+    pub async fn ddb_delete_all(state: &AppState, entry_sk: &str) -> AResult<()> {
+        let requests = ArchiveRead::ddb_list(state, entry_sk)
+            .await?
+            .into_iter()
+            .map(|read| delete_request(ddb_key(read.pk, read.sk)))
+            .collect::<AResult<Vec<_>>>()?;
+        ddb_batch_write(state, requests).await
+    }
+}
+
+impl ArchiveEntry {
+    /// Counts a read of every entry without grading it and logs it, leaving the review
+    /// schedule as is. All entries are marked in one transaction, so it fails as a whole
+    /// when any of them no longer exists.
+    pub async fn ddb_mark_read_all(
+        state: &AppState,
+        mut entries: Vec<ArchiveEntry>,
+    ) -> AResult<Vec<ArchiveEntry>> {
+        if entries.is_empty() {
+            return Ok(entries);
+        }
+        if entries.len() * 2 > TRANSACT_WRITE_LIMIT {
+            return Err(anyhow::Error::msg(format!(
+                "Can't mark more than {} entries as read at once",
+                TRANSACT_WRITE_LIMIT / 2
+            ))
+            .into());
+        }
+
+        let mut actions = Vec::new();
+        let mut reads = Vec::new();
+        for entry in &entries {
+            let read = ArchiveRead::new(&entry.sk, None);
+            let update = Update::builder()
+                .table_name(&state.table_name)
+                .set_key(Some(ddb_key(&entry.pk, &entry.sk)))
+                .update_expression("SET last_read = :read ADD read_times :one")
+                .condition_expression("attribute_exists(pk)")
+                .expression_attribute_values(":read", AttributeValue::S(read.sk.clone()))
+                .expression_attribute_values(":one", AttributeValue::N(String::from("1")))
+                .build()?;
+            let put = Put::builder()
+                .table_name(&state.table_name)
+                .set_item(Some(to_item(&read)?))
+                .condition_expression("attribute_not_exists(pk)")
+                .build()?;
+            actions.push(TransactWriteItem::builder().update(update).build());
+            actions.push(TransactWriteItem::builder().put(put).build());
+            reads.push(read);
+        }
+
+        let res = state
+            .dynamodb_client
+            .transact_write_items()
+            .set_transact_items(Some(actions))
+            .send()
+            .await;
+        match res {
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|e| e.is_transaction_canceled_exception()) =>
+            {
+                Err(
+                    anyhow::Error::msg("Archive entries were deleted while marking them as read")
+                        .into(),
+                )
+            }
+            Err(err) => Err(err.into()),
+            Ok(_) => {
+                for (entry, read) in entries.iter_mut().zip(reads) {
+                    entry.read_times += 1;
+                    entry.last_read = Some(read.sk);
+                }
+                Ok(entries)
+            }
+        }
+    }
+}
+
+impl ReadHistory {
+    /// Returns None when there is no entry with the sort key.
+    pub async fn ddb_find(state: &AppState, sk: impl Into<String>) -> AResult<Option<ReadHistory>> {
+        let Ok(entry) = ArchiveEntry::ddb_find(state, sk).await else {
+            return Ok(None);
+        };
+        let mut reads = ArchiveRead::ddb_list(state, &entry.sk).await?;
+        reads.reverse();
+        Ok(Some(ReadHistory {
+            sk: entry.sk,
+            read_times: entry.read_times,
+            last_read: entry.last_read,
+            reads,
+        }))
+    }
+}
@@ -0,0 +1,217 @@
+use aws_sdk_dynamodb::types::{AttributeValue, Put, TransactWriteItem, Update};
+use chrono::{Duration, NaiveDate, Utc};
+use serde::Deserialize;
+use serde_dynamo::{from_items, to_item};
+
+use super::reads::ArchiveRead;
+use super::{ArchiveEntry, ARCHIVE_SK};
+use crate::utils::ddb::{ddb_key, ddb_query_partition};
+use crate::utils::time::TIMEZONE;
+use crate::{AResult, AppState};
+
+pub const DEFAULT_EASE: f64 = 2.5;
+const MIN_EASE: f64 = 1.3;
+pub const MAX_GRADE: u8 = 5;
+// grades below this mean the entry wasn't recalled and its repetitions start over
+const PASSING_GRADE: u8 = 3;
+
+pub fn default_ease() -> f64 {
+    DEFAULT_EASE
+}
+
+pub enum ReviewOutcome {
+    Reviewed(Box<ArchiveEntry>),
+    NotFound,
+    Conflict,
+}
+
+/// Recall grade of a review, from 0 (complete blackout) to 5 (perfect recall).
+#[derive(Deserialize)]
+pub struct ReviewFC {
+    pub grade: u8,
+}
+
+fn today() -> NaiveDate {
+    Utc::now().with_timezone(&TIMEZONE).date_naive()
+}
+
+impl ArchiveEntry {
+    /// Entries created before scheduling existed have no due date and are due right away.
+    pub fn is_due(&self, today: NaiveDate) -> bool {
+        self.due
+            .as_deref()
+            .and_then(|due| NaiveDate::parse_from_str(due, "%Y-%m-%d").ok())
+            .is_none_or(|due| due <= today)
+    }
+
+    /// Reschedules the entry following SM-2: a passing grade grows the interval by the ease
+    /// factor, a failing one starts over with a one day interval. The ease factor moves with
+    /// every grade and never drops below 1.3.
+    pub fn schedule(&mut self, grade: u8, today: NaiveDate) -> AResult<()> {
+        if grade > MAX_GRADE {
+            return Err(
+                anyhow::Error::msg(format!("Grade must be between 0 and {}", MAX_GRADE)).into(),
+            );
+        }
+
+        if grade < PASSING_GRADE {
+            self.repetitions = 0;
+            self.interval = 1;
+        } else {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f64 * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+        }
+
+        let miss = (MAX_GRADE - grade) as f64;
+        let ease = self.ease + 0.1 - miss * (0.08 + miss * 0.02);
+        self.ease = ((ease * 100.0).round() / 100.0).max(MIN_EASE);
+        self.due = Some((today + Duration::days(self.interval as i64)).to_string());
+        self.read_times += 1;
+        Ok(())
+    }
+
+    /// Today's review queue, the longest overdue entries first.
+    pub async fn ddb_find_due(state: &AppState) -> AResult<Vec<ArchiveEntry>> {
+        let today = today();
+        let items = ddb_query_partition(state, ARCHIVE_SK).await?;
+        let entries: Vec<ArchiveEntry> = from_items(items)?;
+        let mut due: Vec<ArchiveEntry> = entries.into_iter().filter(|e| e.is_due(today)).collect();
+        due.sort_by(|a, b| a.due.cmp(&b.due).then_with(|| a.sk.cmp(&b.sk)));
+        Ok(due)
+    }
+
+    /// Saves the new schedule and logs the read in one transaction. `read_times` is
+    /// incremented in place, and the update is rejected when another review of the entry
+    /// was saved since it was read.
+    pub async fn ddb_review(
+        state: &AppState,
+        sk: impl Into<String>,
+        review: ReviewFC,
+    ) -> AResult<ReviewOutcome> {
+        let Ok(mut entry) = ArchiveEntry::ddb_find(state, sk).await else {
+            return Ok(ReviewOutcome::NotFound);
+        };
+        let previous_read = entry.last_read.take();
+        entry.schedule(review.grade, today())?;
+        let read = ArchiveRead::new(&entry.sk, Some(review.grade));
+        entry.last_read = Some(read.sk.clone());
+
+        let mut update = Update::builder()
+            .table_name(&state.table_name)
+            .set_key(Some(ddb_key(&entry.pk, &entry.sk)))
+            .update_expression(
+                "SET ease = :ease, #interval = :interval, repetitions = :repetitions, \
+                 due = :due, last_read = :read ADD read_times :one",
+            )
+            .expression_attribute_names("#interval", "interval")
+            .expression_attribute_values(":ease", AttributeValue::N(entry.ease.to_string()))
+            .expression_attribute_values(":interval", AttributeValue::N(entry.interval.to_string()))
+            .expression_attribute_values(
+                ":repetitions",
+                AttributeValue::N(entry.repetitions.to_string()),
+            )
+            .expression_attribute_values(":due", AttributeValue::S(entry.due.clone().unwrap()))
+            .expression_attribute_values(":read", AttributeValue::S(read.sk.clone()))
+            .expression_attribute_values(":one", AttributeValue::N(String::from("1")));
+        update = match previous_read {
+            Some(previous_read) => update
+                .condition_expression("attribute_exists(pk) AND last_read = :previous_read")
+                .expression_attribute_values(":previous_read", AttributeValue::S(previous_read)),
+            None => update
+                .condition_expression("attribute_exists(pk) AND attribute_not_exists(last_read)"),
+        };
+        let put = Put::builder()
+            .table_name(&state.table_name)
+            .set_item(Some(to_item(&read)?))
+            .condition_expression("attribute_not_exists(pk)")
+            .build()?;
+
+        let res = state
+            .dynamodb_client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().update(update.build()?).build())
+            .transact_items(TransactWriteItem::builder().put(put).build())
+            .send()
+            .await;
+        match res {
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|e| e.is_transaction_canceled_exception()) =>
+            {
+                Ok(ReviewOutcome::Conflict)
+            }
+            Err(err) => Err(err.into()),
+            Ok(_) => Ok(ReviewOutcome::Reviewed(Box::new(entry))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(due: Option<&str>) -> ArchiveEntry {
+        ArchiveEntry {
+            pk: String::from(ARCHIVE_SK),
+            sk: String::from("2024-05-01T08:30:00+02:00"),
+            content: String::from("Festina lente"),
+            categories: None,
+            read_times: 0,
+            ease: DEFAULT_EASE,
+            interval: 0,
+            repetitions: 0,
+            due: due.map(String::from),
+            last_read: None,
+            source: None,
+        }
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_schedule() {
+        let today = date("2024-05-01");
+        let mut entry = entry(None);
+
+        entry.schedule(5, today).unwrap();
+        assert_eq!((entry.interval, entry.repetitions), (1, 1));
+        assert_eq!(entry.ease, 2.6);
+        assert_eq!(entry.due.as_deref(), Some("2024-05-02"));
+
+        entry.schedule(4, today).unwrap();
+        assert_eq!((entry.interval, entry.repetitions), (6, 2));
+        assert_eq!(entry.ease, 2.6);
+
+        entry.schedule(3, today).unwrap();
+        assert_eq!(entry.interval, 16);
+        assert_eq!(entry.ease, 2.46);
+        assert_eq!(entry.due.as_deref(), Some("2024-05-17"));
+
+        entry.schedule(1, today).unwrap();
+        assert_eq!((entry.interval, entry.repetitions), (1, 0));
+        assert_eq!(entry.ease, 1.92);
+        assert_eq!(entry.read_times, 4);
+
+        entry.schedule(0, today).unwrap();
+        entry.schedule(0, today).unwrap();
+        assert_eq!(entry.ease, MIN_EASE);
+
+        assert!(entry.schedule(6, today).is_err());
+    }
+
+    #[test]
+    fn test_is_due() {
+        let today = date("2024-05-01");
+        assert!(entry(None).is_due(today));
+        assert!(entry(Some("2024-04-20")).is_due(today));
+        assert!(entry(Some("2024-05-01")).is_due(today));
+        assert!(!entry(Some("2024-05-02")).is_due(today));
+    }
+}
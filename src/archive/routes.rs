@@ -1,34 +1,190 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    routing::{delete, get, post, put},
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, patch, post},
     Json, Router,
 };
+use serde::Deserialize;
 use serde_json::{json, Value};
 
 use crate::{AResult, AppState};
 
-use super::{ArchiveEntry, ArchiveEntryFC};
+use super::{
+    to_anki_tsv, AnkiExportParams, ArchiveCreate, ArchiveEntry, ArchiveEntryFC, ArchiveEntryFU,
+    ArchiveUpdate, CategoryCount, CategoryRename, KindleImport, KindleImportReport, RandomParams,
+    ReadHistory, ReviewFC, ReviewOutcome, MAX_GRADE,
+};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/all", get(find_all))
+        .route("/", get(find_by_category))
         .route("/", post(create_handler))
         .route("/:sk", delete(delete_handler))
-        .route("/increment/:sk", put(increment_read_times_handler))
+        .route("/:sk", patch(update_handler))
+        .route("/due", get(find_due))
+        .route("/random", get(find_random))
+        .route("/:sk/review", post(review_handler))
+        .route("/:sk/reads", get(read_history))
+        .route("/import/kindle", post(import_kindle))
+        .route("/export/anki", get(export_anki))
+        .route("/categories", get(list_categories))
+        .route("/categories/rename", post(rename_category))
+        .route("/categories/reindex", post(reindex_categories))
+        .route("/dedup/reindex", post(reindex_dedup))
 }
 
 async fn find_all(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
     let response = ArchiveEntry::ddb_find_all(state).await?;
-    return Ok((StatusCode::OK, Json(json!(response))));
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+#[derive(Deserialize)]
+struct CategoryParams {
+    category: Option<String>,
+}
+
+async fn find_by_category(
+    State(state): State<AppState>,
+    Query(params): Query<CategoryParams>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let response = match params.category {
+        Some(category) => ArchiveEntry::ddb_find_by_category(&state, category.trim()).await?,
+        None => ArchiveEntry::ddb_find_all(state).await?,
+    };
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn find_random(
+    State(state): State<AppState>,
+    Query(params): Query<RandomParams>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let response = ArchiveEntry::ddb_random(&state, params).await?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn import_kindle(
+    State(state): State<AppState>,
+    Json(payload): Json<KindleImport>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let dry_run = payload.dry_run;
+    let response = KindleImportReport::ddb_import(&state, payload).await?;
+    let status = match dry_run {
+        true => StatusCode::OK,
+        false => StatusCode::CREATED,
+    };
+    Ok((status, Json(json!(response))))
+}
+
+async fn export_anki(
+    State(state): State<AppState>,
+    Query(params): Query<AnkiExportParams>,
+) -> AResult<Response> {
+    let entries = ArchiveEntry::ddb_find_for_export(&state, params.category.as_deref()).await?;
+    Ok((
+        StatusCode::OK,
+        [
+            (
+                header::CONTENT_TYPE,
+                "text/tab-separated-values; charset=utf-8",
+            ),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"archive_anki.txt\"",
+            ),
+        ],
+        to_anki_tsv(&entries)?,
+    )
+        .into_response())
+}
+
+async fn list_categories(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
+    let response = CategoryCount::ddb_list(&state).await?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn rename_category(
+    State(state): State<AppState>,
+    Json(payload): Json<CategoryRename>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    if !payload.is_valid() {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(
+                json!({ "message": "Categories must be non-empty and different from each other" }),
+            ),
+        ));
+    }
+    let report = ArchiveEntry::ddb_rename_category(&state, &payload).await?;
+    Ok((StatusCode::OK, Json(json!(report))))
+}
+
+async fn reindex_categories(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
+    let indexed = ArchiveEntry::ddb_rebuild_categories(&state).await?;
+    Ok((StatusCode::OK, Json(json!({ "indexed": indexed }))))
+}
+
+async fn reindex_dedup(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
+    let report = ArchiveEntry::ddb_rebuild_dedup_markers(&state).await?;
+    Ok((StatusCode::OK, Json(json!(report))))
 }
 
 async fn create_handler(
     State(state): State<AppState>,
     Json(payload): Json<ArchiveEntryFC>,
-) -> AResult<StatusCode> {
-    ArchiveEntry::ddb_create(&state, payload).await?;
-    return Ok(StatusCode::CREATED);
+) -> AResult<(StatusCode, Json<Value>)> {
+    if payload.content.trim().is_empty() {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "message": "Content cannot be empty" })),
+        ));
+    }
+    match ArchiveEntry::ddb_create(&state, payload).await? {
+        ArchiveCreate::Created(entry) => Ok((StatusCode::CREATED, Json(json!(entry)))),
+        ArchiveCreate::Duplicate(existing) => Ok((
+            StatusCode::CONFLICT,
+            Json(json!({
+                "message": "The same content from the same source is already archived",
+                "sk": existing.sk
+            })),
+        )),
+    }
+}
+
+async fn update_handler(
+    State(state): State<AppState>,
+    Path(sk): Path<String>,
+    Json(payload): Json<ArchiveEntryFU>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    if payload
+        .content
+        .as_deref()
+        .is_some_and(|c| c.trim().is_empty())
+    {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "message": "Content cannot be empty" })),
+        ));
+    }
+    match ArchiveEntry::ddb_update(&state, sk, payload).await? {
+        ArchiveUpdate::Updated(entry) => Ok((StatusCode::OK, Json(json!(entry)))),
+        ArchiveUpdate::NotFound => Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "message": "ArchiveEntry with provided sort key does not exist" })),
+        )),
+        ArchiveUpdate::Conflict => Ok((
+            StatusCode::CONFLICT,
+            Json(json!({ "message": "ArchiveEntry changed meanwhile, fetch it and retry" })),
+        )),
+        ArchiveUpdate::Duplicate(existing) => Ok((
+            StatusCode::CONFLICT,
+            Json(json!({
+                "message": "The same content from the same source is already archived",
+                "sk": existing.sk
+            })),
+        )),
+    }
 }
 
 async fn delete_handler(
@@ -36,13 +192,49 @@ async fn delete_handler(
     Path(sk): Path<String>,
 ) -> AResult<StatusCode> {
     ArchiveEntry::ddb_delete(&state, sk).await?;
-    return Ok(StatusCode::NO_CONTENT);
+    Ok(StatusCode::NO_CONTENT)
 }
 
-async fn increment_read_times_handler(
+async fn find_due(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
+    let response = ArchiveEntry::ddb_find_due(&state).await?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn review_handler(
     State(state): State<AppState>,
     Path(sk): Path<String>,
-) -> AResult<StatusCode> {
-    ArchiveEntry::ddb_increment_read_times(&state, sk).await?;
-    return Ok(StatusCode::OK);
+    Json(payload): Json<ReviewFC>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    if payload.grade > MAX_GRADE {
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "message": format!("Grade must be between 0 and {}", MAX_GRADE) })),
+        ));
+    }
+    match ArchiveEntry::ddb_review(&state, sk, payload).await? {
+        ReviewOutcome::Reviewed(entry) => Ok((StatusCode::OK, Json(json!(entry)))),
+        ReviewOutcome::NotFound => Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "message": "ArchiveEntry with provided sort key does not exist" })),
+        )),
+        ReviewOutcome::Conflict => Ok((
+            StatusCode::CONFLICT,
+            Json(
+                json!({ "message": "ArchiveEntry was reviewed by another request, fetch it and retry" }),
+            ),
+        )),
+    }
+}
+
+async fn read_history(
+    State(state): State<AppState>,
+    Path(sk): Path<String>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    match ReadHistory::ddb_find(&state, sk).await? {
+        Some(history) => Ok((StatusCode::OK, Json(json!(history)))),
+        None => Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "message": "ArchiveEntry with provided sort key does not exist" })),
+        )),
+    }
 }
@@ -0,0 +1,7 @@
+mod model;
+mod routes;
+mod store;
+
+pub use model::Attachment;
+pub use routes::router;
+pub use store::{BlobStore, LocalBlobStore, S3BlobStore};
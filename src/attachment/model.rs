@@ -0,0 +1,275 @@
+use aws_sdk_dynamodb::types::{AttributeValue, ReturnValue};
+use axum::http::StatusCode;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_dynamo::{from_item, from_items, to_item};
+
+use crate::archive::ARCHIVE_SK;
+use crate::utils::ddb::{
+    ddb_batch_write, ddb_key, ddb_put_new, ddb_query_partition, delete_request,
+};
+use crate::utils::id::ulid;
+use crate::utils::time::get_today_datetime;
+use crate::{AResult, AppState};
+
+// API Gateway base64-encodes binary bodies, so 4 MiB grows to about 5.6 MB, below the
+// 6 MB Lambda payload limit of both the upload and the download
+pub const MAX_ATTACHMENT_SIZE: usize = 4 * 1024 * 1024;
+
+const ALLOWED_CONTENT_TYPES: [&str; 12] = [
+    "image/jpeg",
+    "image/png",
+    "image/gif",
+    "image/webp",
+    "image/heic",
+    "application/pdf",
+    "audio/mpeg",
+    "audio/mp4",
+    "audio/aac",
+    "audio/ogg",
+    "audio/wav",
+    "audio/webm",
+];
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Attachment {
+    pub pk: String, // "Attachment::<owner pk>#<owner sk>", e.g. "Attachment::Entry::Dream#2024-05-01"
+    pub sk: String, // attachment id, a ULID, e.g. "01HWSDGZNV8D3Q6V2M1PJ4X7TR"
+    pub owner_pk: String,
+    pub owner_sk: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size: u64,
+    pub blob_key: String, // key in the BlobStore
+    pub created: String,
+}
+
+#[derive(Deserialize)]
+pub struct UploadParams {
+    pub filename: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum UploadError {
+    Empty,
+    TooLarge,
+    UnsupportedType(String),
+}
+
+impl UploadError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            UploadError::Empty => StatusCode::BAD_REQUEST,
+            UploadError::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::UnsupportedType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            UploadError::Empty => String::from("Attachment must not be empty"),
+            UploadError::TooLarge => format!(
+                "Attachment must not be larger than {} bytes",
+                MAX_ATTACHMENT_SIZE
+            ),
+            UploadError::UnsupportedType(t) => format!("Unsupported content type '{}'", t),
+        }
+    }
+}
+
+/// Strips parameters from the content type, e.g. "audio/ogg; codecs=opus" -> "audio/ogg",
+/// and checks it against the allowed types.
+pub fn validate_upload(content_type: &str, size: usize) -> Result<String, UploadError> {
+    if size == 0 {
+        return Err(UploadError::Empty);
+    }
+    if size > MAX_ATTACHMENT_SIZE {
+        return Err(UploadError::TooLarge);
+    }
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    if !ALLOWED_CONTENT_TYPES.contains(&essence.as_str()) {
+        return Err(UploadError::UnsupportedType(content_type.to_string()));
+    }
+    Ok(essence)
+}
+
+// keeps only the file name, without directories or characters that would break headers
+fn sanitize_filename(filename: Option<&str>) -> String {
+    let name: String = filename
+        .unwrap_or_default()
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .collect();
+    match name.trim() {
+        "" | "." | ".." => String::from("attachment"),
+        name => name.to_string(),
+    }
+}
+
+pub fn attachment_pk(owner_pk: &str, owner_sk: &str) -> String {
+    format!("Attachment::{}#{}", owner_pk, owner_sk)
+}
+
+impl Attachment {
+    pub fn new(
+        owner_pk: &str,
+        owner_sk: &str,
+        filename: Option<&str>,
+        content_type: String,
+        size: usize,
+    ) -> Attachment {
+        let id = ulid(Utc::now(), &mut rand::thread_rng());
+        Attachment {
+            pk: attachment_pk(owner_pk, owner_sk),
+            blob_key: format!("attachments/{}/{}/{}", owner_pk, owner_sk, id),
+            sk: id,
+            owner_pk: owner_pk.to_string(),
+            owner_sk: owner_sk.to_string(),
+            filename: sanitize_filename(filename),
+            content_type,
+            size: size as u64,
+            created: get_today_datetime(),
+        }
+    }
+}
+
+// DynamoDB handlers
+impl Attachment {
+    /// Attachments can belong to an Entry or an ArchiveEntry that exists.
+    pub async fn ddb_owner_exists(
+        state: &AppState,
+        owner_pk: &str,
+        owner_sk: &str,
+    ) -> AResult<bool> {
+        if !owner_pk.starts_with("Entry::") && owner_pk != ARCHIVE_SK {
+            return Ok(false);
+        }
+        let res = state
+            .dynamodb_client
+            .get_item()
+            .table_name(&state.table_name)
+            .key("pk", AttributeValue::S(owner_pk.to_string()))
+            .key("sk", AttributeValue::S(owner_sk.to_string()))
+            .projection_expression("pk")
+            .send()
+            .await?;
+        Ok(res.item.is_some())
+    }
+
+    /// Stores the blob first, so that metadata never points to missing content.
+    pub async fn ddb_create(
+        state: &AppState,
+        attachment: &Attachment,
+        bytes: &[u8],
+    ) -> AResult<()> {
+        state.blob_store.put(&attachment.blob_key, bytes).await?;
+        ddb_put_new(state, to_item(attachment)?).await
+    }
+
+    pub async fn ddb_list(
+        state: &AppState,
+        owner_pk: &str,
+        owner_sk: &str,
+    ) -> AResult<Vec<Attachment>> {
+        let items = ddb_query_partition(state, attachment_pk(owner_pk, owner_sk)).await?;
+        Ok(from_items(items)?)
+    }
+
+    pub async fn ddb_find(
+        state: &AppState,
+        owner_pk: &str,
+        owner_sk: &str,
+        id: &str,
+    ) -> AResult<Option<Attachment>> {
+        let res = state
+            .dynamodb_client
+            .get_item()
+            .table_name(&state.table_name)
+            .key("pk", AttributeValue::S(attachment_pk(owner_pk, owner_sk)))
+            .key("sk", AttributeValue::S(id.to_string()))
+            .send()
+            .await?;
+        match res.item {
+            Some(item) => Ok(Some(from_item(item)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns `false` if there was no such attachment.
+    pub async fn ddb_delete(
+        state: &AppState,
+        owner_pk: &str,
+        owner_sk: &str,
+        id: &str,
+    ) -> AResult<bool> {
+        let res = state
+            .dynamodb_client
+            .delete_item()
+            .table_name(&state.table_name)
+            .key("pk", AttributeValue::S(attachment_pk(owner_pk, owner_sk)))
+            .key("sk", AttributeValue::S(id.to_string()))
+            .return_values(ReturnValue::AllOld)
+            .send()
+            .await?;
+        match res.attributes {
+            Some(item) => {
+                let attachment: Attachment = from_item(item)?;
+                state.blob_store.delete(&attachment.blob_key).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Removes every attachment of a deleted Entry or ArchiveEntry, blobs included.
+    pub async fn ddb_delete_all(state: &AppState, owner_pk: &str, owner_sk: &str) -> AResult<()> {
+        let attachments = Attachment::ddb_list(state, owner_pk, owner_sk).await?;
+        let mut requests = Vec::new();
+        for attachment in attachments {
+            state.blob_store.delete(&attachment.blob_key).await?;
+            requests.push(delete_request(ddb_key(attachment.pk, attachment.sk))?);
+        }
+        ddb_batch_write(state, requests).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_upload() {
+        assert_eq!(
+            validate_upload("Audio/Ogg; codecs=opus", 10),
+            Ok(String::from("audio/ogg"))
+        );
+        assert_eq!(validate_upload("image/png", 0), Err(UploadError::Empty));
+        assert_eq!(
+            validate_upload("image/png", MAX_ATTACHMENT_SIZE + 1),
+            Err(UploadError::TooLarge)
+        );
+        assert!(matches!(
+            validate_upload("text/html", 10),
+            Err(UploadError::UnsupportedType(_))
+        ));
+    }
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename(Some("../../etc/passwd")), "passwd");
+        assert_eq!(
+            sanitize_filename(Some("C:\\photos\\cat \"1\".jpg")),
+            "cat 1.jpg"
+        );
+        assert_eq!(sanitize_filename(Some("..")), "attachment");
+        assert_eq!(sanitize_filename(None), "attachment");
+    }
+}
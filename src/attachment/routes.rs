@@ -0,0 +1,97 @@
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Path, Query, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use super::model::{validate_upload, UploadParams, MAX_ATTACHMENT_SIZE};
+use super::Attachment;
+use crate::{AResult, AppState};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/:owner_pk/:owner_sk",
+            post(upload).layer(DefaultBodyLimit::max(MAX_ATTACHMENT_SIZE)),
+        )
+        .route("/:owner_pk/:owner_sk", get(list))
+        .route("/:owner_pk/:owner_sk/:id", get(download))
+        .route("/:owner_pk/:owner_sk/:id", delete(delete_attachment))
+}
+
+async fn upload(
+    State(state): State<AppState>,
+    Path((owner_pk, owner_sk)): Path<(String, String)>,
+    Query(params): Query<UploadParams>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let content_type = match validate_upload(content_type, body.len()) {
+        Ok(content_type) => content_type,
+        Err(e) => return Ok((e.status(), Json(json!({ "message": e.message() })))),
+    };
+
+    if !Attachment::ddb_owner_exists(&state, &owner_pk, &owner_sk).await? {
+        return Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "message": "Entry or ArchiveEntry with provided key does not exist" })),
+        ));
+    }
+
+    let attachment = Attachment::new(
+        &owner_pk,
+        &owner_sk,
+        params.filename.as_deref(),
+        content_type,
+        body.len(),
+    );
+    Attachment::ddb_create(&state, &attachment, &body).await?;
+    Ok((StatusCode::CREATED, Json(json!(attachment))))
+}
+
+async fn list(
+    State(state): State<AppState>,
+    Path((owner_pk, owner_sk)): Path<(String, String)>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let response = Attachment::ddb_list(&state, &owner_pk, &owner_sk).await?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn download(
+    State(state): State<AppState>,
+    Path((owner_pk, owner_sk, id)): Path<(String, String, String)>,
+) -> AResult<Response> {
+    let Some(attachment) = Attachment::ddb_find(&state, &owner_pk, &owner_sk, &id).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+    let Some(bytes) = state.blob_store.get(&attachment.blob_key).await? else {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    };
+
+    let disposition = format!("inline; filename=\"{}\"", attachment.filename);
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, attachment.content_type),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        bytes,
+    )
+        .into_response())
+}
+
+async fn delete_attachment(
+    State(state): State<AppState>,
+    Path((owner_pk, owner_sk, id)): Path<(String, String, String)>,
+) -> AResult<StatusCode> {
+    if !Attachment::ddb_delete(&state, &owner_pk, &owner_sk, &id).await? {
+        return Ok(StatusCode::NOT_FOUND);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
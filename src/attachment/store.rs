@@ -0,0 +1,144 @@
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+
+use async_trait::async_trait;
+use aws_sdk_s3::primitives::ByteStream;
+
+use crate::AResult;
+
+/// Storage of attachment contents. Metadata lives in DynamoDB, blobs only under `key`.
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    async fn put(&self, key: &str, bytes: &[u8]) -> AResult<()>;
+    /// Returns `None` if there is no blob with given key.
+    async fn get(&self, key: &str) -> AResult<Option<Vec<u8>>>;
+    /// Deleting a missing blob is not an error.
+    async fn delete(&self, key: &str) -> AResult<()>;
+}
+
+/// Keeps blobs as files under `root`, for local development and tests.
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        LocalBlobStore { root: root.into() }
+    }
+
+    // keys are relative paths made of plain segments, so they can't escape the root
+    fn path_of(&self, key: &str) -> AResult<PathBuf> {
+        let path = Path::new(key);
+        let is_plain = path.components().all(|c| matches!(c, Component::Normal(_)));
+        if key.is_empty() || !is_plain {
+            return Err(anyhow::Error::msg(format!("Invalid blob key '{}'", key)).into());
+        }
+        Ok(self.root.join(path))
+    }
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, key: &str, bytes: &[u8]) -> AResult<()> {
+        let path = self.path_of(key)?;
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        tokio::fs::write(path, bytes).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> AResult<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path_of(key)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> AResult<()> {
+        match tokio::fs::remove_file(self.path_of(key)?).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Keeps blobs as objects of an S3 bucket, used by the Lambda.
+pub struct S3BlobStore {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl S3BlobStore {
+    pub fn new(client: aws_sdk_s3::Client, bucket: impl Into<String>) -> Self {
+        S3BlobStore {
+            client,
+            bucket: bucket.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put(&self, key: &str, bytes: &[u8]) -> AResult<()> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(ByteStream::from(bytes.to_vec()))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> AResult<Option<Vec<u8>>> {
+        let res = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await;
+        match res {
+            Ok(object) => Ok(Some(object.body.collect().await?.into_bytes().to_vec())),
+            Err(err) if err.as_service_error().is_some_and(|e| e.is_no_such_key()) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // S3 doesn't report missing keys on delete
+    async fn delete(&self, key: &str) -> AResult<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_blob_store() {
+        let root = std::env::temp_dir().join(format!("vault-blobs-{}", std::process::id()));
+        let store = LocalBlobStore::new(&root);
+        let key = "attachments/Entry::Dream/2024-05-01/1.png";
+
+        assert_eq!(store.get(key).await.unwrap(), None);
+        store.put(key, b"png").await.unwrap();
+        assert_eq!(store.get(key).await.unwrap(), Some(b"png".to_vec()));
+        store.delete(key).await.unwrap();
+        store.delete(key).await.unwrap();
+        assert_eq!(store.get(key).await.unwrap(), None);
+
+        assert!(store.put("../outside", b"x").await.is_err());
+        assert!(store.put("/etc/passwd", b"x").await.is_err());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
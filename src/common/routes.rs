@@ -1,23 +1,49 @@
-use axum::{extract::State, routing::get, Json, Router};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::{routing::get, Json, Router};
 use serde_json::{json, Value};
 
 use crate::{
-    entry::find_last_week_entries, record::find_last_week_records, task::find_last_week_tasks,
+    entry::{entries_stats_in_window, find_entries_in_window},
+    mood::find_moods_in_window,
+    record::find_records_in_window,
+    task::find_tasks_in_window,
+    utils::time::{get_today_date, DateWindow, WindowParams},
     AResult, AppState,
 };
 
 pub fn router() -> Router<AppState> {
-    Router::new().route("/", get(common_last_week_handler))
+    Router::new().route("/", get(common_handler))
 }
 
-async fn common_last_week_handler(State(state): State<AppState>) -> AResult<Json<Value>> {
-    let tasks = find_last_week_tasks(&state).await?;
-    let records = find_last_week_records(&state).await?;
-    let entries = find_last_week_entries(&state).await?;
+/// Dashboard data of the last week, or of `?days=N` or `?from=&to=`.
+async fn common_handler(
+    State(state): State<AppState>,
+    Query(params): Query<WindowParams>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let window = match DateWindow::from_params(&params, get_today_date()) {
+        Ok(window) => window,
+        Err(message) => {
+            return Ok((StatusCode::BAD_REQUEST, Json(json!({ "message": message }))));
+        }
+    };
 
-    Ok(Json(json!({
-        "tasks_data": tasks,
-        "records_data": records,
-        "entries_data": entries,
-    })))
+    let tasks = find_tasks_in_window(&state, &window).await?;
+    let records = find_records_in_window(&state, &window).await?;
+    let entries = find_entries_in_window(&state, &window).await?;
+    let entries_stats = entries_stats_in_window(&entries, &window)?;
+    let moods = find_moods_in_window(&state, &window).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "from": window.from,
+            "to": window.to,
+            "tasks_data": tasks,
+            "records_data": records,
+            "entries_data": entries,
+            "entries_stats": entries_stats,
+            "moods_data": moods,
+        })),
+    ))
 }
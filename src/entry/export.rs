@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Cursor, Write};
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::Entry;
+use crate::entryproto::EntryProto;
+use crate::{AResult, AppState};
+
+#[derive(Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Markdown, // single document with one section per day
+    Zip, // one `YYYY-MM-DD.md` file per day, Obsidian daily-note layout
+}
+
+#[derive(Deserialize)]
+pub struct ExportParams {
+    pub from: String, // e.g. "2024-05-01"
+    pub to: String,   // e.g. "2024-05-31"
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+impl ExportParams {
+    /// Parsed `from` and `to`, the error message is meant for the client.
+    pub fn dates(&self) -> Result<(NaiveDate, NaiveDate), String> {
+        let parse = |date: &str| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", date))
+        };
+        let (from, to) = (parse(&self.from)?, parse(&self.to)?);
+        if from > to {
+            return Err(String::from("from must not be after to"));
+        }
+        Ok((from, to))
+    }
+}
+
+// date -> journal title -> entries
+type Days<'a> = BTreeMap<String, BTreeMap<String, Vec<&'a Entry>>>;
+
+pub struct EntryExport {
+    pub entries: Vec<Entry>,
+    pub titles: HashMap<String, String>, // EntryProto sort key -> current EntryProto title
+}
+
+impl EntryExport {
+    /// Collects entries of every EntryProto, active and inactive, within the given date range.
+    pub async fn ddb_find(state: &AppState, from: &str, to: &str) -> AResult<EntryExport> {
+        let protos = EntryProto::ddb_list_all(state).await?;
+
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut titles: HashMap<String, String> = HashMap::new();
+        for proto in protos {
+            entries.extend(Entry::ddb_query_from_to(state, &proto.sk, from, to).await?);
+            titles.insert(proto.sk, proto.title);
+        }
+        Ok(EntryExport { entries, titles })
+    }
+
+    fn group_by_day(&self) -> Days<'_> {
+        let mut days: Days = BTreeMap::new();
+        for entry in &self.entries {
+            let title = self.titles.get(&entry.pk).unwrap_or(&entry.title);
+            days.entry(entry.sk.chars().take(10).collect())
+                .or_default()
+                .entry(title.clone())
+                .or_default()
+                .push(entry);
+        }
+        days
+    }
+
+    pub fn to_markdown(&self, from: &str, to: &str, exported_at: &str) -> String {
+        let mut doc = format!(
+            "---\ntitle: Journal export\nfrom: {}\nto: {}\nexported_at: {}\nentries: {}\n---\n",
+            from,
+            to,
+            exported_at,
+            self.entries.len()
+        );
+        for (date, journals) in self.group_by_day() {
+            doc.push('\n');
+            doc.push_str(&render_day(&date, &journals));
+        }
+        doc
+    }
+
+    pub fn to_daily_notes_zip(&self) -> AResult<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        for (date, journals) in self.group_by_day() {
+            let titles: Vec<&str> = journals.keys().map(|t| t.as_str()).collect();
+            let note = format!(
+                "---\ndate: {}\ntags: [journal]\njournals: [{}]\n---\n\n{}",
+                date,
+                titles.join(", "),
+                render_day(&date, &journals)
+            );
+            zip.start_file(format!("{}.md", date), options)?;
+            zip.write_all(note.as_bytes())?;
+        }
+        Ok(zip.finish()?.into_inner())
+    }
+}
+
+fn render_day(date: &str, journals: &BTreeMap<String, Vec<&Entry>>) -> String {
+    let mut section = format!("# {}\n", date);
+    for (title, entries) in journals {
+        section.push_str(&format!("\n## {}\n", title));
+        for entry in entries {
+            if let Some(fields) = &entry.fields {
+                section.push('\n');
+                for (key, value) in fields {
+                    section.push_str(&format!("- {}: {}\n", key, value));
+                }
+            }
+            if !entry.content.trim().is_empty() {
+                section.push_str(&format!("\n{}\n", entry.content.trim_end()));
+            }
+        }
+    }
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entryproto::FieldValue;
+
+    fn entry(pk: &str, sk: &str, title: &str, content: &str) -> Entry {
+        Entry {
+            pk: pk.to_string(),
+            sk: sk.to_string(),
+            title: title.to_string(),
+            content: content.to_string(),
+            tags: None,
+            fields: None,
+        }
+    }
+
+    fn export() -> EntryExport {
+        EntryExport {
+            entries: vec![
+                entry("Entry::Dream", "2024-05-02", "Dream", "Flying again"),
+                entry("Entry::Dream", "2024-05-01", "Dream", "I was flying\n"),
+                entry("Entry::Gratitude", "2024-05-01", "Thanks", "Coffee"),
+                Entry {
+                    fields: Some(BTreeMap::from([
+                        (String::from("energy"), FieldValue::Number(7.0)),
+                        (String::from("rested"), FieldValue::Boolean(true)),
+                    ])),
+                    ..entry("Entry::Sleep", "2024-05-02", "Sleep", "")
+                },
+            ],
+            titles: HashMap::from([
+                ("Entry::Sleep".to_string(), "Sleep".to_string()),
+                ("Entry::Dream".to_string(), "Dream".to_string()),
+                ("Entry::Gratitude".to_string(), "Gratitude".to_string()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_dates() {
+        let params = |from: &str, to: &str| ExportParams {
+            from: from.to_string(),
+            to: to.to_string(),
+            format: ExportFormat::Zip,
+        };
+        let (from, to) = params("2024-05-01", "2024-05-31").dates().unwrap();
+        assert_eq!(
+            (from.to_string(), to.to_string()),
+            ("2024-05-01".into(), "2024-05-31".into())
+        );
+        assert!(params("2024-05-01", "2024-05-01").dates().is_ok());
+        assert!(params("2024-05-31", "2024-05-01").dates().is_err());
+        assert!(params("2024-05-01\"; x", "2024-05-31").dates().is_err());
+    }
+
+    #[test]
+    fn test_to_markdown() {
+        let md = export().to_markdown("2024-05-01", "2024-05-02", "2024-05-03T10:00:00Z");
+        assert_eq!(
+            md,
+            "---\ntitle: Journal export\nfrom: 2024-05-01\nto: 2024-05-02\nexported_at: 2024-05-03T10:00:00Z\nentries: 4\n---\n\
+            \n# 2024-05-01\n\n## Dream\n\nI was flying\n\n## Gratitude\n\nCoffee\n\
+            \n# 2024-05-02\n\n## Dream\n\nFlying again\n\n## Sleep\n\n- energy: 7\n- rested: yes\n"
+        );
+    }
+
+    #[test]
+    fn test_to_daily_notes_zip() {
+        let bytes = export().to_daily_notes_zip().unwrap();
+        let archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(names, vec!["2024-05-01.md", "2024-05-02.md"]);
+    }
+}
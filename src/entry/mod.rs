@@ -1,7 +1,13 @@
+mod export;
 mod model;
 mod routes;
+mod stats;
+mod tag;
 
 pub use model::Entry;
 pub use model::EntryFC;
-pub use routes::find_last_week_entries;
+pub use model::EntryPage;
+pub use routes::find_entries_in_window;
 pub use routes::router;
+pub use stats::{entries_stats_in_window, EntryStats, EntryStatsSummary};
+pub use tag::{EntryTag, TagCount};
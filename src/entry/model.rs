@@ -1,8 +1,15 @@
-use aws_sdk_dynamodb::types::AttributeValue;
+use std::collections::{BTreeMap, HashMap};
+
+use aws_sdk_dynamodb::types::{AttributeValue, ReturnValue, TransactWriteItem, Update};
 use serde::{Deserialize, Serialize};
-use serde_dynamo::{from_items, to_item};
+use serde_dynamo::{from_item, from_items, to_item};
+
+use super::tag::{collect_tags, EntryTag};
 
-use crate::entryproto::EntryProto;
+use crate::attachment::Attachment;
+use crate::entryproto::{validate_answers, EntryProto, FieldValue};
+use crate::search::SearchDoc;
+use crate::utils::ddb::{ddb_key, ddb_transact_each, DdbItem};
 use crate::utils::time::get_date_x_days_ago;
 use crate::{AResult, AppState};
 
@@ -12,12 +19,37 @@ pub struct Entry {
     pub sk: String, // creation date in ISO 8601 format, e.g. "2021-08-01T00:00:00Z"
     pub title: String,
     pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>, // e.g. ["travel", "family"]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<BTreeMap<String, FieldValue>>, // answers to EntryProto fields, by field key
+}
+
+#[derive(Serialize)]
+pub struct EntryPage {
+    pub entries: Vec<Entry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>, // cursor of the next page, e.g. "Entry::Dream|2024-05-01"
 }
 
+const CURSOR_SEPARATOR: char = '|';
+
 #[derive(Deserialize)]
 pub struct EntryFC {
     pub pk: String,
+    #[serde(default)]
     pub content: String,
+    pub tags: Option<Vec<String>>, // merged with #hashtags found in content
+    pub fields: Option<HashMap<String, FieldValue>>,
+}
+
+// content may only be left out when the entry has answers to EntryProto fields,
+// so entries of protos without fields always have content
+fn validate_content(content: &str, fields: Option<&BTreeMap<String, FieldValue>>) -> AResult<()> {
+    if content.trim().is_empty() && fields.is_none() {
+        return Err(anyhow::Error::msg("Entry content must not be empty").into());
+    }
+    Ok(())
 }
 
 impl Entry {
@@ -38,12 +70,133 @@ impl Entry {
         match res.items {
             Some(items) => {
                 let entries: Vec<Entry> = from_items(items)?;
-                return Ok(entries);
+                Ok(entries)
             }
-            None => {
-                return Err(anyhow::Error::msg("Error querying DynamoDB entries. {:?}").into());
+            None => Err(anyhow::Error::msg("Error querying DynamoDB entries. {:?}").into()),
+        }
+    }
+
+    pub async fn ddb_query_from_to(
+        state: &AppState,
+        pk: impl Into<String>,
+        from: impl Into<String>,
+        to: impl Into<String>,
+    ) -> AResult<Vec<Entry>> {
+        let items: Vec<DdbItem> = state
+            .dynamodb_client
+            .query()
+            .table_name(&state.table_name)
+            .key_condition_expression("pk = :pk AND sk BETWEEN :from AND :to")
+            .expression_attribute_values(":pk", AttributeValue::S(pk.into()))
+            .expression_attribute_values(":from", AttributeValue::S(from.into()))
+            .expression_attribute_values(":to", AttributeValue::S(to.into()))
+            .into_paginator()
+            .items()
+            .send()
+            .try_collect()
+            .await?;
+
+        Ok(from_items(items)?)
+    }
+
+    /// Reads up to `limit` entries of given partitions between `from` and `to`, ordered by
+    /// partition and date. Returns a cursor to pass back for the next page, if there is one.
+    pub async fn ddb_query_page(
+        state: &AppState,
+        pks: &[String],
+        from: &str,
+        to: &str,
+        limit: usize,
+        cursor: Option<&str>,
+    ) -> AResult<EntryPage> {
+        let cursor = match cursor {
+            Some(c) => Some(
+                c.split_once(CURSOR_SEPARATOR)
+                    .ok_or(anyhow::Error::msg("Invalid Entry page cursor"))?,
+            ),
+            None => None,
+        };
+        let mut entries: Vec<Entry> = Vec::new();
+
+        for (i, pk) in pks.iter().enumerate() {
+            let mut start_key: Option<DdbItem> = match cursor {
+                Some((cursor_pk, _)) if pk.as_str() < cursor_pk => continue,
+                Some((cursor_pk, cursor_sk)) if pk == cursor_pk => {
+                    Some(ddb_key(cursor_pk, cursor_sk))
+                }
+                _ => None,
+            };
+
+            loop {
+                let res = state
+                    .dynamodb_client
+                    .query()
+                    .table_name(&state.table_name)
+                    .key_condition_expression("pk = :pk AND sk BETWEEN :from AND :to")
+                    .expression_attribute_values(":pk", AttributeValue::S(pk.clone()))
+                    .expression_attribute_values(":from", AttributeValue::S(from.to_string()))
+                    .expression_attribute_values(":to", AttributeValue::S(to.to_string()))
+                    .limit((limit - entries.len()) as i32)
+                    .set_exclusive_start_key(start_key)
+                    .send()
+                    .await?;
+
+                let page: Vec<Entry> = from_items(res.items.unwrap_or_default())?;
+                entries.extend(page);
+                start_key = res.last_evaluated_key;
+
+                if entries.len() >= limit {
+                    let has_more = start_key.is_some() || i + 1 < pks.len();
+                    let next = match entries.last() {
+                        Some(last) if has_more => {
+                            Some(format!("{}{}{}", last.pk, CURSOR_SEPARATOR, last.sk))
+                        }
+                        _ => None,
+                    };
+                    return Ok(EntryPage { entries, next });
+                }
+                if start_key.is_none() {
+                    break;
+                }
+            }
+        }
+        Ok(EntryPage {
+            entries,
+            next: None,
+        })
+    }
+
+    /// Counts entries of given partitions between `from` and `to` per day.
+    pub async fn ddb_count_by_day(
+        state: &AppState,
+        pks: &[String],
+        from: &str,
+        to: &str,
+    ) -> AResult<BTreeMap<String, u64>> {
+        let mut days: BTreeMap<String, u64> = BTreeMap::new();
+        for pk in pks {
+            let items: Vec<DdbItem> = state
+                .dynamodb_client
+                .query()
+                .table_name(&state.table_name)
+                .key_condition_expression("pk = :pk AND sk BETWEEN :from AND :to")
+                .expression_attribute_values(":pk", AttributeValue::S(pk.clone()))
+                .expression_attribute_values(":from", AttributeValue::S(from.to_string()))
+                .expression_attribute_values(":to", AttributeValue::S(to.to_string()))
+                .projection_expression("sk")
+                .into_paginator()
+                .items()
+                .send()
+                .try_collect()
+                .await?;
+
+            for item in items {
+                if let Some(AttributeValue::S(sk)) = item.get("sk") {
+                    *days.entry(sk.chars().take(10).collect()).or_insert(0) += 1;
+                }
             }
         }
+        Ok(days)
     }
 
     pub async fn ddb_put_item(state: &AppState, entry_fc: EntryFC) -> AResult<()> {
@@ -58,40 +211,134 @@ impl Entry {
             }
         };
 
+        let fields = validate_answers(
+            entry_proto.fields.as_deref().unwrap_or_default(),
+            entry_fc.fields,
+        )?;
+        validate_content(&entry_fc.content, fields.as_ref())?;
+        let tags = collect_tags(entry_fc.tags.as_deref(), &entry_fc.content)?;
         let entry = Entry {
             pk: entry_proto.sk,
             sk: get_date_x_days_ago(0),
             title: entry_proto.title,
             content: entry_fc.content,
+            tags: (!tags.is_empty()).then_some(tags),
+            fields,
         };
-        let item = to_item(entry)?;
-        state
+        let item = to_item(&entry)?;
+        let res = state
             .dynamodb_client
             .put_item()
             .table_name(&state.table_name)
             .set_item(Some(item))
+            .return_values(ReturnValue::AllOld)
             .send()
             .await?;
+
+        let old_tags = tags_of(res.attributes)?;
+        EntryTag::ddb_sync(
+            state,
+            &entry.pk,
+            &entry.sk,
+            &old_tags,
+            entry.tags.as_deref().unwrap_or_default(),
+        )
+        .await?;
+        SearchDoc::ddb_reindex(state, entry.pk, entry.sk, Some(&entry.content)).await?;
         Ok(())
     }
 
+    /// Sets `title` on every entry of the partition in transactions of up to 100 updates,
+    /// updating only the title so concurrent edits of the content aren't lost. Returns the
+    /// number of rewritten entries.
+    pub async fn ddb_rewrite_title(state: &AppState, pk: &str, title: &str) -> AResult<usize> {
+        let mut pages = state
+            .dynamodb_client
+            .query()
+            .table_name(&state.table_name)
+            .key_condition_expression("pk = :pk")
+            .expression_attribute_values(":pk", AttributeValue::S(pk.to_string()))
+            .projection_expression("sk, title")
+            .into_paginator()
+            .send();
+
+        let mut actions = Vec::new();
+        while let Some(page) = pages.next().await {
+            for item in page?.items.unwrap_or_default() {
+                if item.get("title") == Some(&AttributeValue::S(title.to_string())) {
+                    continue;
+                }
+                let Some(AttributeValue::S(sk)) = item.get("sk") else {
+                    continue;
+                };
+                let update = Update::builder()
+                    .table_name(&state.table_name)
+                    .set_key(Some(ddb_key(pk, sk)))
+                    .update_expression("SET title = :title")
+                    .condition_expression("attribute_exists(pk)")
+                    .expression_attribute_values(":title", AttributeValue::S(title.to_string()))
+                    .build()?;
+                actions.push(TransactWriteItem::builder().update(update).build());
+            }
+        }
+
+        // entries deleted since the query have nothing to rewrite
+        let count = actions.len();
+        let deleted = ddb_transact_each(state, actions).await?;
+        Ok(count - deleted.len())
+    }
+
     pub async fn ddb_delete(
         state: &AppState,
         pk: impl Into<String>,
         sk: impl Into<String>,
     ) -> AResult<()> {
         let pk = pk.into();
+        let sk = sk.into();
         if !pk.starts_with("Entry::") {
             return Err(anyhow::Error::msg("Invalid Entry primary key").into());
         }
-        state
+        let res = state
             .dynamodb_client
             .delete_item()
             .table_name(&state.table_name)
-            .key("pk", AttributeValue::S(pk))
-            .key("sk", AttributeValue::S(sk.into()))
+            .key("pk", AttributeValue::S(pk.clone()))
+            .key("sk", AttributeValue::S(sk.clone()))
+            .return_values(ReturnValue::AllOld)
             .send()
             .await?;
+
+        EntryTag::ddb_sync(state, &pk, &sk, &tags_of(res.attributes)?, &[]).await?;
+        SearchDoc::ddb_unindex(state, &pk, &sk).await?;
+        Attachment::ddb_delete_all(state, &pk, &sk).await?;
         Ok(())
     }
 }
+
+// tags of an entry returned by a put or delete, empty if there was no such entry
+fn tags_of(attributes: Option<DdbItem>) -> AResult<Vec<String>> {
+    match attributes {
+        Some(item) => {
+            let entry: Entry = from_item(item)?;
+            Ok(entry.tags.unwrap_or_default())
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_content() {
+        // a proto without fields stores no answers
+        let no_fields = validate_answers(&[], None).unwrap();
+        assert!(validate_content("Flying over the sea", no_fields.as_ref()).is_ok());
+        assert!(validate_content("", no_fields.as_ref()).is_err());
+        assert!(validate_content(" \n", no_fields.as_ref()).is_err());
+
+        let fields = BTreeMap::from([(String::from("lucid"), FieldValue::Boolean(true))]);
+        assert!(validate_content("", Some(&fields)).is_ok());
+    }
+}
@@ -1,26 +1,33 @@
 use std::collections::HashMap;
 
 use aws_sdk_dynamodb::types::{AttributeValue, KeysAndAttributes};
-use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::routing::{delete, get, put};
 use axum::{Json, Router};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use serde_dynamo::from_items;
 use serde_json::{json, Value};
 
-use super::{Entry, EntryFC};
+use super::export::{EntryExport, ExportFormat, ExportParams};
+use super::tag::TagQueryParams;
+use super::{Entry, EntryFC, EntryTag, TagCount};
 use crate::entryproto::EntryProto;
-use crate::utils::time::get_date_x_days_ago;
+use crate::utils::time::{get_today_date, get_today_datetime, DateWindow, WindowParams};
 use crate::{AResult, AppState};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", put(put_item))
+        .route("/", get(query_range))
         .route("/:pk/:sk", delete(delete_entry))
         .route("/:pk/:sk", get(query))
         .route("/:date", get(find_by_date))
         .route("/last-week", get(find_last_week_handler))
+        .route("/export", get(export))
+        .route("/tags", get(list_tags))
+        .route("/tags/:tag", get(find_by_tag))
 }
 
 #[derive(Serialize)]
@@ -32,18 +39,22 @@ pub struct ProtoWithEntries {
 async fn find_last_week_handler(
     State(state): State<AppState>,
 ) -> AResult<(StatusCode, Json<Value>)> {
-    let result_entries = find_last_week_entries(&state).await?;
+    let window = DateWindow::last_days(7, get_today_date());
+    let result_entries = find_entries_in_window(&state, &window).await?;
 
-    return Ok((StatusCode::OK, Json(json!(result_entries))));
+    Ok((StatusCode::OK, Json(json!(result_entries))))
 }
 
-pub async fn find_last_week_entries(state: &AppState) -> AResult<Vec<ProtoWithEntries>> {
+pub async fn find_entries_in_window(
+    state: &AppState,
+    window: &DateWindow,
+) -> AResult<Vec<ProtoWithEntries>> {
     let active_ep = EntryProto::ddb_list_active(state).await?;
-    let week_ago = get_date_x_days_ago(7);
     let mut result_entries: Vec<ProtoWithEntries> = Vec::new();
 
     for entry_proto in active_ep {
-        let t: Vec<Entry> = Entry::ddb_query(&state, &entry_proto.sk, &week_ago).await?;
+        let t: Vec<Entry> =
+            Entry::ddb_query_from_to(state, &entry_proto.sk, &window.from, window.sk_to()).await?;
         result_entries.push(ProtoWithEntries {
             proto: entry_proto,
             entries: t,
@@ -93,16 +104,148 @@ async fn find_by_date(
 
     Ok((
         StatusCode::OK,
-        Json(json!({ "entries": entries, "proto": active_ep })),
+        Json(json!(FindEntriesResult {
+            entries,
+            proto: active_ep
+        })),
     ))
 }
 
+const DEFAULT_PAGE_LIMIT: usize = 100;
+const MAX_PAGE_LIMIT: usize = 1000;
+
+#[derive(Deserialize)]
+struct RangeQueryParams {
+    from: String,          // e.g. "2024-05-01"
+    to: String,            // e.g. "2024-05-31"
+    proto: Option<String>, // e.g. "Entry::Dream", all EntryProtos if not given
+    limit: Option<usize>,
+    cursor: Option<String>,
+    #[serde(default)]
+    count: bool, // return number of entries per day instead of entries
+}
+
+async fn query_range(
+    State(state): State<AppState>,
+    Query(params): Query<RangeQueryParams>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let dates = WindowParams {
+        days: None,
+        from: Some(params.from),
+        to: Some(params.to),
+    };
+    let window = match DateWindow::from_params(&dates, get_today_date()) {
+        Ok(window) => window,
+        Err(message) => {
+            return Ok((StatusCode::BAD_REQUEST, Json(json!({ "message": message }))));
+        }
+    };
+
+    let pks: Vec<String> = match params.proto {
+        Some(pk) if pk.starts_with("Entry::") => vec![pk],
+        Some(_) => {
+            return Ok((
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "message": "Invalid EntryProto sort key" })),
+            ));
+        }
+        None => EntryProto::ddb_list_all(&state)
+            .await?
+            .into_iter()
+            .map(|ep| ep.sk)
+            .collect(),
+    };
+
+    if params.count {
+        let days = Entry::ddb_count_by_day(&state, &pks, &window.from, &window.to).await?;
+        let total: u64 = days.values().sum();
+        return Ok((
+            StatusCode::OK,
+            Json(json!({
+                "from": window.from,
+                "to": window.to,
+                "total": total,
+                "days": days
+            })),
+        ));
+    }
+
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_PAGE_LIMIT)
+        .clamp(1, MAX_PAGE_LIMIT);
+    let page = Entry::ddb_query_page(
+        &state,
+        &pks,
+        &window.from,
+        &window.to,
+        limit,
+        params.cursor.as_deref(),
+    )
+    .await?;
+    Ok((StatusCode::OK, Json(json!(page))))
+}
+
+async fn list_tags(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
+    let response = TagCount::ddb_list(&state).await?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn find_by_tag(
+    State(state): State<AppState>,
+    Path(tag): Path<String>,
+    Query(params): Query<TagQueryParams>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let entries = EntryTag::ddb_find_entries(&state, &tag, &params).await?;
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "tag": tag, "entries": entries })),
+    ))
+}
+
+async fn export(
+    State(state): State<AppState>,
+    Query(params): Query<ExportParams>,
+) -> AResult<Response> {
+    // the dates end up in the file name, so only parsed ones are used
+    let (from, to) = match params.dates() {
+        Ok((from, to)) => (from.to_string(), to.to_string()),
+        Err(message) => {
+            return Ok(
+                (StatusCode::BAD_REQUEST, Json(json!({ "message": message }))).into_response(),
+            );
+        }
+    };
+    let export = EntryExport::ddb_find(&state, &from, &to).await?;
+
+    if params.format == ExportFormat::Zip {
+        let disposition = format!("attachment; filename=\"journal_{}_{}.zip\"", from, to);
+        return Ok((
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            export.to_daily_notes_zip()?,
+        )
+            .into_response());
+    }
+
+    let markdown = export.to_markdown(&from, &to, &get_today_datetime());
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+        markdown,
+    )
+        .into_response())
+}
+
 async fn query(
     State(state): State<AppState>,
     Path((pk, sk)): Path<(String, String)>,
 ) -> AResult<(StatusCode, Json<Value>)> {
     let response = Entry::ddb_query(&state, pk, sk).await?;
-    return Ok((StatusCode::OK, Json(json!(response))));
+    Ok((StatusCode::OK, Json(json!(response))))
 }
 
 async fn put_item(
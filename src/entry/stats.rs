@@ -0,0 +1,236 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::Serialize;
+use serde_dynamo::from_items;
+
+use super::routes::ProtoWithEntries;
+use super::Entry;
+use crate::entryproto::EntryProto;
+use crate::utils::ddb::ddb_query_partition;
+use crate::utils::time::{get_today_date, DateWindow};
+use crate::{AResult, AppState};
+
+#[derive(Serialize)]
+pub struct EntryWordCount {
+    pub sk: String, // e.g. "2024-05-01"
+    pub words: usize,
+}
+
+#[derive(Serialize, Default, PartialEq, Debug)]
+pub struct PeriodStats {
+    pub entries: usize,
+    pub words: usize,
+}
+
+#[derive(Serialize)]
+pub struct EntryStats {
+    pub sk: String, // EntryProto sort key, e.g. "Entry::Dream"
+    pub title: String,
+    pub total_entries: usize,
+    pub total_words: usize,
+    pub average_words: f64,
+    pub days_written: usize,
+    pub current_streak: u32, // consecutive days with an entry, ending today or yesterday
+    pub longest_streak: u32,
+    pub per_entry: Vec<EntryWordCount>,
+    pub per_week: BTreeMap<String, PeriodStats>, // by ISO week, e.g. "2024-W18"
+    pub per_month: BTreeMap<String, PeriodStats>, // e.g. "2024-05"
+}
+
+/// Compact version of EntryStats shown on the common dashboard.
+#[derive(Serialize)]
+pub struct EntryStatsSummary {
+    pub sk: String,
+    pub title: String,
+    pub total_entries: usize,
+    pub days_written: usize,
+    pub average_words: f64,
+    pub current_streak: u32,
+    pub longest_streak: u32,
+}
+
+impl From<EntryStats> for EntryStatsSummary {
+    fn from(stats: EntryStats) -> Self {
+        EntryStatsSummary {
+            sk: stats.sk,
+            title: stats.title,
+            total_entries: stats.total_entries,
+            days_written: stats.days_written,
+            average_words: stats.average_words,
+            current_streak: stats.current_streak,
+            longest_streak: stats.longest_streak,
+        }
+    }
+}
+
+pub fn count_words(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+fn entry_date(entry: &Entry) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(entry.sk.get(..10)?, "%Y-%m-%d").ok()
+}
+
+// (current, longest) run of consecutive days; the current run may end yesterday,
+// so an entry that is not written yet today doesn't reset it
+fn compute_streaks(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> (u32, u32) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut prev: Option<NaiveDate> = None;
+    for day in days {
+        run = match prev {
+            Some(p) if *day - p == Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        prev = Some(*day);
+    }
+
+    let current = match prev {
+        Some(last) if last == today || last == today - Duration::days(1) => run,
+        _ => 0,
+    };
+    (current, longest)
+}
+
+impl EntryStats {
+    pub fn compute(proto: &EntryProto, entries: &[Entry], today: NaiveDate) -> EntryStats {
+        let mut per_entry: Vec<EntryWordCount> = Vec::new();
+        let mut per_week: BTreeMap<String, PeriodStats> = BTreeMap::new();
+        let mut per_month: BTreeMap<String, PeriodStats> = BTreeMap::new();
+        let mut days: BTreeSet<NaiveDate> = BTreeSet::new();
+
+        for entry in entries {
+            let words = count_words(&entry.content);
+            per_entry.push(EntryWordCount {
+                sk: entry.sk.clone(),
+                words,
+            });
+
+            let Some(date) = entry_date(entry) else {
+                continue;
+            };
+            days.insert(date);
+
+            let week = date.iso_week();
+            for period in [
+                per_week
+                    .entry(format!("{}-W{:02}", week.year(), week.week()))
+                    .or_default(),
+                per_month
+                    .entry(date.format("%Y-%m").to_string())
+                    .or_default(),
+            ] {
+                period.entries += 1;
+                period.words += words;
+            }
+        }
+
+        let total_words: usize = per_entry.iter().map(|e| e.words).sum();
+        let average_words = if entries.is_empty() {
+            0.0
+        } else {
+            total_words as f64 / entries.len() as f64
+        };
+        let (current_streak, longest_streak) = compute_streaks(&days, today);
+
+        EntryStats {
+            sk: proto.sk.clone(),
+            title: proto.title.clone(),
+            total_entries: entries.len(),
+            total_words,
+            average_words,
+            days_written: days.len(),
+            current_streak,
+            longest_streak,
+            per_entry,
+            per_week,
+            per_month,
+        }
+    }
+
+    pub async fn ddb_compute(state: &AppState, proto: &EntryProto) -> AResult<EntryStats> {
+        let entries: Vec<Entry> = from_items(ddb_query_partition(state, &proto.sk).await?)?;
+        Ok(EntryStats::compute(proto, &entries, get_today_date()))
+    }
+}
+
+/// Stats of the entries already read for the dashboard window, whose last day counts as
+/// today for the current streak.
+pub fn entries_stats_in_window(
+    entries: &[ProtoWithEntries],
+    window: &DateWindow,
+) -> AResult<Vec<EntryStatsSummary>> {
+    let to = NaiveDate::parse_from_str(&window.to, "%Y-%m-%d")?;
+    Ok(entries
+        .iter()
+        .map(|e| EntryStats::compute(&e.proto, &e.entries, to).into())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(sk: &str, content: &str) -> Entry {
+        Entry {
+            pk: String::from("Entry::Dream"),
+            sk: sk.to_string(),
+            title: String::from("Dream"),
+            content: content.to_string(),
+            tags: None,
+            fields: None,
+        }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_compute_streaks() {
+        let days: BTreeSet<NaiveDate> = ["2024-04-28", "2024-04-29", "2024-04-30", "2024-05-02"]
+            .iter()
+            .map(|d| date(d))
+            .collect();
+        assert_eq!(compute_streaks(&days, date("2024-05-02")), (1, 3));
+        assert_eq!(compute_streaks(&days, date("2024-05-03")), (1, 3));
+        assert_eq!(compute_streaks(&days, date("2024-05-04")), (0, 3));
+        assert_eq!(
+            compute_streaks(&BTreeSet::new(), date("2024-05-04")),
+            (0, 0)
+        );
+    }
+
+    #[test]
+    fn test_compute() {
+        let proto = EntryProto {
+            pk: String::from("EntryProto::Active"),
+            sk: String::from("Entry::Dream"),
+            title: String::from("Dream"),
+            fields: None,
+        };
+        let entries = vec![
+            entry("2024-04-29", "I was flying"),
+            entry("2024-04-30", "Ocean  again\nand again"),
+            entry("2024-05-01", "Falling"),
+        ];
+        let stats = EntryStats::compute(&proto, &entries, date("2024-05-01"));
+
+        assert_eq!(stats.total_entries, 3);
+        assert_eq!(stats.total_words, 8);
+        assert!((stats.average_words - 8.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(stats.days_written, 3);
+        assert_eq!((stats.current_streak, stats.longest_streak), (3, 3));
+        assert_eq!(
+            stats.per_week.get("2024-W18"),
+            Some(&PeriodStats {
+                entries: 3,
+                words: 8
+            })
+        );
+        assert_eq!(stats.per_month.get("2024-04").map(|p| p.words), Some(7));
+        assert_eq!(stats.per_month.get("2024-05").map(|p| p.words), Some(1));
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::BTreeSet;
+
+use aws_sdk_dynamodb::types::{AttributeValue, ReturnValue, WriteRequest};
+use serde::{Deserialize, Serialize};
+use serde_dynamo::{from_items, to_item};
+
+use super::Entry;
+use crate::utils::ddb::{
+    ddb_batch_get, ddb_batch_write, ddb_key, ddb_query_partition, delete_request, put_request,
+    DdbItem,
+};
+use crate::{AResult, AppState};
+
+pub const TAG_COUNT_PK: &str = "Tag::Count";
+pub const TAG_PK_PREFIX: &str = "Tag::Entry::";
+
+const MAX_TAG_LEN: usize = 64;
+
+/// Index item linking a tag with a tagged entry.
+#[derive(Serialize, Deserialize)]
+pub struct EntryTag {
+    pub pk: String, // e.g. "Tag::Entry::travel"
+    pub sk: String, // "<entry sk>#<entry pk>", e.g. "2024-05-01#Entry::Dream"
+    pub entry_pk: String,
+    pub entry_sk: String,
+}
+
+/// Number of entries tagged with given tag.
+#[derive(Serialize, Deserialize)]
+pub struct TagCount {
+    #[serde(skip_serializing)]
+    pub pk: String, // "Tag::Count"
+    #[serde(rename(serialize = "tag"))]
+    pub sk: String, // e.g. "travel"
+    pub count: i64,
+}
+
+#[derive(Deserialize)]
+pub struct TagQueryParams {
+    pub from: Option<String>, // e.g. "2024-05-01"
+    pub to: Option<String>,   // e.g. "2024-05-31"
+}
+
+/// Lowercases a tag and strips a leading `#`. Returns `None` for tags with invalid characters.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim().trim_start_matches('#').to_lowercase();
+    let valid = !tag.is_empty()
+        && tag.chars().count() <= MAX_TAG_LEN
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    valid.then_some(tag)
+}
+
+/// Finds `#hashtags` in text. A hashtag has to start a word, so "C#" or "a#b" are ignored.
+pub fn parse_hashtags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    let mut prev: Option<char> = None;
+    for (i, c) in text.char_indices() {
+        if c == '#' && prev.is_none_or(char::is_whitespace) {
+            let rest = &text[i + 1..];
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+                .unwrap_or(rest.len());
+            if let Some(tag) = normalize_tag(rest[..end].trim_end_matches('-')) {
+                tags.push(tag);
+            }
+        }
+        prev = Some(c);
+    }
+    tags
+}
+
+/// Merges explicitly given tags with hashtags found in content, sorted and deduplicated.
+pub fn collect_tags(given: Option<&[String]>, content: &str) -> AResult<Vec<String>> {
+    let mut tags: BTreeSet<String> = parse_hashtags(content).into_iter().collect();
+    for tag in given.unwrap_or_default() {
+        match normalize_tag(tag) {
+            Some(tag) => tags.insert(tag),
+            None => return Err(anyhow::Error::msg(format!("Invalid tag: {}", tag)).into()),
+        };
+    }
+    Ok(tags.into_iter().collect())
+}
+
+impl EntryTag {
+    fn new(tag: &str, entry_pk: &str, entry_sk: &str) -> Self {
+        EntryTag {
+            pk: format!("{}{}", TAG_PK_PREFIX, tag),
+            sk: format!("{}#{}", entry_sk, entry_pk),
+            entry_pk: entry_pk.to_string(),
+            entry_sk: entry_sk.to_string(),
+        }
+    }
+
+    /// Updates tag index and counters after tags of an entry changed from `old` to `new`.
+    pub async fn ddb_sync(
+        state: &AppState,
+        entry_pk: &str,
+        entry_sk: &str,
+        old: &[String],
+        new: &[String],
+    ) -> AResult<()> {
+        let mut requests: Vec<WriteRequest> = Vec::new();
+        let removed: Vec<&String> = old.iter().filter(|t| !new.contains(t)).collect();
+        let added: Vec<&String> = new.iter().filter(|t| !old.contains(t)).collect();
+
+        for tag in &removed {
+            let index = EntryTag::new(tag, entry_pk, entry_sk);
+            requests.push(delete_request(ddb_key(index.pk, index.sk))?);
+        }
+        for tag in &added {
+            requests.push(put_request(to_item(EntryTag::new(
+                tag, entry_pk, entry_sk,
+            ))?)?);
+        }
+        ddb_batch_write(state, requests).await?;
+
+        for tag in removed {
+            TagCount::ddb_add(state, tag, -1).await?;
+        }
+        for tag in added {
+            TagCount::ddb_add(state, tag, 1).await?;
+        }
+        Ok(())
+    }
+
+    /// Finds entries of all EntryProtos tagged with `tag`, optionally within a date range.
+    pub async fn ddb_find_entries(
+        state: &AppState,
+        tag: &str,
+        params: &TagQueryParams,
+    ) -> AResult<Vec<Entry>> {
+        let tag = normalize_tag(tag).ok_or(anyhow::Error::msg("Invalid tag"))?;
+        // '~' sorts after '#', so "<to>~" includes every index item of the last day
+        let from = params.from.clone().unwrap_or(String::from("0"));
+        let to = format!("{}~", params.to.clone().unwrap_or(String::from("9999")));
+
+        let items: Vec<DdbItem> = state
+            .dynamodb_client
+            .query()
+            .table_name(&state.table_name)
+            .key_condition_expression("pk = :pk AND sk BETWEEN :from AND :to")
+            .expression_attribute_values(
+                ":pk",
+                AttributeValue::S(format!("{}{}", TAG_PK_PREFIX, tag)),
+            )
+            .expression_attribute_values(":from", AttributeValue::S(from))
+            .expression_attribute_values(":to", AttributeValue::S(to))
+            .into_paginator()
+            .items()
+            .send()
+            .try_collect()
+            .await?;
+        let index: Vec<EntryTag> = from_items(items)?;
+
+        let keys: Vec<DdbItem> = index
+            .iter()
+            .map(|t| ddb_key(&t.entry_pk, &t.entry_sk))
+            .collect();
+        let mut entries: Vec<Entry> = from_items(ddb_batch_get(state, keys).await?)?;
+        entries.sort_by(|a, b| a.sk.cmp(&b.sk).then(a.pk.cmp(&b.pk)));
+        Ok(entries)
+    }
+}
+
+impl TagCount {
+    async fn ddb_add(state: &AppState, tag: &str, delta: i64) -> AResult<()> {
+        let res = state
+            .dynamodb_client
+            .update_item()
+            .table_name(&state.table_name)
+            .key("pk", AttributeValue::S(String::from(TAG_COUNT_PK)))
+            .key("sk", AttributeValue::S(tag.to_string()))
+            .update_expression("ADD #count :delta")
+            .expression_attribute_names("#count", "count")
+            .expression_attribute_values(":delta", AttributeValue::N(delta.to_string()))
+            .return_values(ReturnValue::UpdatedNew)
+            .send()
+            .await?;
+
+        let count: i64 = match res.attributes.as_ref().and_then(|a| a.get("count")) {
+            Some(AttributeValue::N(n)) => n.parse()?,
+            _ => 0,
+        };
+        if count > 0 {
+            return Ok(());
+        }
+
+        // only remove the counter if nobody re-tagged an entry in the meantime
+        let res = state
+            .dynamodb_client
+            .delete_item()
+            .table_name(&state.table_name)
+            .key("pk", AttributeValue::S(String::from(TAG_COUNT_PK)))
+            .key("sk", AttributeValue::S(tag.to_string()))
+            .condition_expression("#count <= :zero")
+            .expression_attribute_names("#count", "count")
+            .expression_attribute_values(":zero", AttributeValue::N(String::from("0")))
+            .send()
+            .await;
+
+        match res {
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|e| e.is_conditional_check_failed_exception()) =>
+            {
+                Ok(())
+            }
+            Err(err) => Err(err.into()),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    pub async fn ddb_list(state: &AppState) -> AResult<Vec<TagCount>> {
+        let mut counts: Vec<TagCount> =
+            from_items(ddb_query_partition(state, TAG_COUNT_PK).await?)?;
+        counts.sort_by(|a, b| b.count.cmp(&a.count).then(a.sk.cmp(&b.sk)));
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hashtags() {
+        assert_eq!(
+            parse_hashtags("#Travel to the sea, learned C# and #rust-lang. #sea-"),
+            vec!["travel", "rust-lang", "sea"]
+        );
+        assert_eq!(parse_hashtags("see a#b and #"), Vec::<String>::new());
+        assert_eq!(parse_hashtags("#żeglowanie!"), vec!["żeglowanie"]);
+    }
+
+    #[test]
+    fn test_collect_tags() {
+        let given = vec![String::from("Sea"), String::from("#family")];
+        assert_eq!(
+            collect_tags(Some(&given), "by the #sea with #Friends").unwrap(),
+            vec!["family", "friends", "sea"]
+        );
+        assert!(collect_tags(Some(&[String::from("two words")]), "").is_err());
+        assert!(collect_tags(None, "no tags").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::AResult;
+
+/// Single prompt of a structured EntryProto, e.g. "How well did you sleep?" rated 1-10.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EntryField {
+    pub key: String,    // e.g. "sleep_quality"
+    pub prompt: String, // e.g. "How well did you sleep?"
+    #[serde(flatten)]
+    pub kind: FieldKind,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum FieldKind {
+    Text,
+    Number { min: f64, max: f64 },
+    Boolean,
+    Choice { options: Vec<String> },
+}
+
+/// Answer to an EntryField, stored as a native DynamoDB type.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum FieldValue {
+    Boolean(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldValue::Boolean(b) => write!(f, "{}", if *b { "yes" } else { "no" }),
+            FieldValue::Number(n) => write!(f, "{}", n),
+            FieldValue::Text(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+pub fn validate_fields(fields: &[EntryField]) -> AResult<()> {
+    let mut keys: HashSet<&str> = HashSet::new();
+    for field in fields {
+        if field.key.trim().is_empty() {
+            return Err(anyhow::Error::msg("EntryProto field key must not be empty").into());
+        }
+        if !keys.insert(&field.key) {
+            return Err(anyhow::Error::msg(format!(
+                "EntryProto field key '{}' is not unique",
+                field.key
+            ))
+            .into());
+        }
+        match &field.kind {
+            FieldKind::Number { min, max } if min > max => {
+                return Err(anyhow::Error::msg(format!(
+                    "EntryProto number field '{}' must have min <= max",
+                    field.key
+                ))
+                .into());
+            }
+            FieldKind::Choice { options } if options.is_empty() => {
+                return Err(anyhow::Error::msg(format!(
+                    "EntryProto choice field '{}' must have at least one option",
+                    field.key
+                ))
+                .into());
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Checks answers against EntryProto fields. Returns `None` if there is nothing to store.
+pub fn validate_answers(
+    fields: &[EntryField],
+    answers: Option<HashMap<String, FieldValue>>,
+) -> AResult<Option<BTreeMap<String, FieldValue>>> {
+    let mut answers = answers.unwrap_or_default();
+    let mut validated: BTreeMap<String, FieldValue> = BTreeMap::new();
+
+    for field in fields {
+        let value = match answers.remove(&field.key) {
+            Some(value) => value,
+            None if field.required => {
+                return Err(
+                    anyhow::Error::msg(format!("Field '{}' is required", field.key)).into(),
+                );
+            }
+            None => continue,
+        };
+
+        let valid = match (&field.kind, &value) {
+            (FieldKind::Text, FieldValue::Text(_)) => true,
+            (FieldKind::Boolean, FieldValue::Boolean(_)) => true,
+            (FieldKind::Number { min, max }, FieldValue::Number(n)) => n >= min && n <= max,
+            (FieldKind::Choice { options }, FieldValue::Text(s)) => options.contains(s),
+            _ => false,
+        };
+        if !valid {
+            return Err(anyhow::Error::msg(format!(
+                "Invalid value for field '{}': {}",
+                field.key, value
+            ))
+            .into());
+        }
+        validated.insert(field.key.clone(), value);
+    }
+
+    if let Some(key) = answers.keys().next() {
+        return Err(anyhow::Error::msg(format!("Unknown field '{}'", key)).into());
+    }
+
+    Ok((!validated.is_empty()).then_some(validated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> Vec<EntryField> {
+        serde_json::from_str(
+            r#"[
+                {"key": "sleep", "prompt": "How well did you sleep?", "type": "number", "min": 1, "max": 10, "required": true},
+                {"key": "lucid", "prompt": "Was it lucid?", "type": "boolean"},
+                {"key": "mood", "prompt": "Mood", "type": "choice", "options": ["calm", "scary"]},
+                {"key": "notes", "prompt": "Anything else?", "type": "text"}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    fn answers(json: &str) -> Option<HashMap<String, FieldValue>> {
+        Some(serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn test_validate_fields() {
+        assert!(validate_fields(&fields()).is_ok());
+
+        let mut duplicated = fields();
+        duplicated[1].key = String::from("sleep");
+        assert!(validate_fields(&duplicated).is_err());
+
+        let mut inverted = fields();
+        inverted[0].kind = FieldKind::Number {
+            min: 10.0,
+            max: 1.0,
+        };
+        assert!(validate_fields(&inverted).is_err());
+    }
+
+    #[test]
+    fn test_dynamodb_roundtrip() {
+        let item: HashMap<String, aws_sdk_dynamodb::types::AttributeValue> =
+            serde_dynamo::to_item(serde_json::json!({ "fields": fields() })).unwrap();
+        let back: HashMap<String, Vec<EntryField>> = serde_dynamo::from_item(item).unwrap();
+        assert!(matches!(
+            back["fields"][0].kind,
+            FieldKind::Number { min, max } if min == 1.0 && max == 10.0
+        ));
+        assert!(matches!(back["fields"][2].kind, FieldKind::Choice { .. }));
+
+        let answers = answers(r#"{"sleep": 7, "lucid": true, "mood": "calm"}"#).unwrap();
+        let item: HashMap<String, aws_sdk_dynamodb::types::AttributeValue> =
+            serde_dynamo::to_item(&answers).unwrap();
+        let back: HashMap<String, FieldValue> = serde_dynamo::from_item(item).unwrap();
+        assert_eq!(back, answers);
+    }
+
+    #[test]
+    fn test_validate_answers() {
+        let res = validate_answers(&fields(), answers(r#"{"sleep": 7, "mood": "calm"}"#))
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.get("sleep"), Some(&FieldValue::Number(7.0)));
+        assert_eq!(
+            res.get("mood"),
+            Some(&FieldValue::Text(String::from("calm")))
+        );
+
+        assert!(validate_answers(&fields(), answers(r#"{"lucid": true}"#)).is_err());
+        assert!(validate_answers(&fields(), answers(r#"{"sleep": 11}"#)).is_err());
+        assert!(validate_answers(&fields(), answers(r#"{"sleep": 5, "mood": "sad"}"#)).is_err());
+        assert!(validate_answers(&fields(), answers(r#"{"sleep": 5, "lucid": "yes"}"#)).is_err());
+        assert!(validate_answers(&fields(), answers(r#"{"sleep": 5, "other": 1}"#)).is_err());
+
+        assert_eq!(validate_answers(&[], None).unwrap(), None);
+        assert!(validate_answers(&[], answers(r#"{"sleep": 5}"#)).is_err());
+    }
+}
@@ -1,6 +1,10 @@
+mod fields;
 mod model;
 mod routes;
 
+pub use fields::{validate_answers, EntryField, FieldKind, FieldValue};
 pub use model::EntryProto;
 pub use model::EntryProtoFC;
+pub use model::EntryProtoFU;
+pub use model::EntryProtoUpdate;
 pub use routes::router;
@@ -1,19 +1,38 @@
+use crate::entry::Entry;
 use crate::{AResult, AppState};
 use aws_sdk_dynamodb::types::AttributeValue;
 use serde::{Deserialize, Serialize};
 use serde_dynamo::{from_item, from_items, to_item};
 
+use super::fields::{validate_fields, EntryField};
+
 #[derive(Serialize, Deserialize)]
 pub struct EntryProto {
     pub pk: String, // "EntryProto::Active" || "EntryProto::Inactive"
     pub sk: String, // Primary key of referenced entry, e.g. "Entry::Dream"
     pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fields: Option<Vec<EntryField>>, // prompts answered in every entry, e.g. "Sleep quality 1-10"
 }
 
 #[derive(Deserialize)]
 pub struct EntryProtoFC {
     pub sk: String,
     pub title: String,
+    pub fields: Option<Vec<EntryField>>,
+}
+
+#[derive(Deserialize)]
+pub struct EntryProtoFU {
+    pub title: Option<String>,
+    pub fields: Option<Vec<EntryField>>,
+    #[serde(default)]
+    pub propagate_title: bool, // rewrite title of all existing entries of this EntryProto
+}
+
+pub enum EntryProtoUpdate {
+    Updated(EntryProto, usize), // with the number of entries whose title was rewritten
+    NotFound,
 }
 
 impl From<EntryProtoFC> for EntryProto {
@@ -22,6 +41,7 @@ impl From<EntryProtoFC> for EntryProto {
             pk: String::from("EntryProto::Active"),
             sk: entry_proto_fc.sk,
             title: entry_proto_fc.title,
+            fields: entry_proto_fc.fields,
         }
     }
 }
@@ -55,7 +75,8 @@ impl EntryProto {
         let entry = EntryProto {
             pk: String::from("EntryProto::Inactive"),
             sk: sk.clone(),
-            title: active_query_res.title.clone(),
+            title: active_query_res.title,
+            fields: active_query_res.fields,
         };
         let item = to_item(entry)?;
         state
@@ -97,7 +118,8 @@ impl EntryProto {
         let entry = EntryProto {
             pk: String::from("EntryProto::Active"),
             sk: sk.clone(),
-            title: inactive_entry.title.clone(),
+            title: inactive_entry.title,
+            fields: inactive_entry.fields,
         };
 
         let item = to_item(entry)?;
@@ -113,6 +135,48 @@ impl EntryProto {
         Ok(())
     }
 
+    /// Updates an active or inactive EntryProto in place.
+    pub async fn update(
+        state: &AppState,
+        sk: impl Into<String>,
+        entry_proto_fu: EntryProtoFU,
+    ) -> AResult<EntryProtoUpdate> {
+        let sk = sk.into();
+        let active = EntryProto::ddb_find(state, "EntryProto::Active", &sk).await;
+        let inactive = EntryProto::ddb_find(state, "EntryProto::Inactive", &sk).await;
+
+        let mut entry_proto = match (active, inactive) {
+            (Ok(_), Ok(_)) => {
+                return Err(anyhow::Error::msg("Corrupted data - EntryProto with given sort key exists in both active and inactive lists").into());
+            }
+            (Ok(res), Err(_)) | (Err(_), Ok(res)) => res,
+            (Err(_), Err(_)) => return Ok(EntryProtoUpdate::NotFound),
+        };
+
+        if let Some(title) = entry_proto_fu.title {
+            entry_proto.title = title;
+        }
+        if let Some(fields) = entry_proto_fu.fields {
+            validate_fields(&fields)?;
+            entry_proto.fields = (!fields.is_empty()).then_some(fields);
+        }
+
+        let item = to_item(&entry_proto)?;
+        state
+            .dynamodb_client
+            .put_item()
+            .table_name(&state.table_name)
+            .set_item(Some(item))
+            .send()
+            .await?;
+
+        let mut updated_entries = 0;
+        if entry_proto_fu.propagate_title {
+            updated_entries = Entry::ddb_rewrite_title(state, &sk, &entry_proto.title).await?;
+        }
+        Ok(EntryProtoUpdate::Updated(entry_proto, updated_entries))
+    }
+
     pub async fn ddb_put_item(state: &AppState, entry_proto_fc: EntryProtoFC) -> AResult<()> {
         if EntryProto::ddb_find(state, "EntryProto::Inactive", &entry_proto_fc.sk)
             .await
@@ -123,11 +187,11 @@ impl EntryProto {
             );
         }
 
-        let entry = EntryProto {
-            pk: String::from("EntryProto::Active"),
-            sk: entry_proto_fc.sk.clone(),
-            title: entry_proto_fc.title,
-        };
+        if let Some(fields) = &entry_proto_fc.fields {
+            validate_fields(fields)?;
+        }
+
+        let entry: EntryProto = entry_proto_fc.into();
         let item = to_item(entry)?;
         state
             .dynamodb_client
@@ -174,11 +238,9 @@ impl EntryProto {
         match res.items {
             Some(items) => {
                 let entries: Vec<EntryProto> = from_items(items)?;
-                return Ok(entries);
-            }
-            None => {
-                return Err(anyhow::Error::msg("Error listing DynamoDB active EntryProto").into());
+                Ok(entries)
             }
+            None => Err(anyhow::Error::msg("Error listing DynamoDB active EntryProto").into()),
         }
     }
 
@@ -229,11 +291,17 @@ impl EntryProto {
         match res.items {
             Some(items) => {
                 let entries: Vec<EntryProto> = from_items(items)?;
-                return Ok(entries);
-            }
-            None => {
-                return Err(anyhow::Error::msg("Error listing DynamoDB active EntryProto").into());
+                Ok(entries)
             }
+            None => Err(anyhow::Error::msg("Error listing DynamoDB active EntryProto").into()),
         }
     }
+
+    /// Lists active and inactive EntryProtos, ordered by sort key.
+    pub async fn ddb_list_all(state: &AppState) -> AResult<Vec<EntryProto>> {
+        let mut protos = EntryProto::ddb_list_active(state).await?;
+        protos.extend(EntryProto::ddb_list_inactive(state).await?);
+        protos.sort_by(|a, b| a.sk.cmp(&b.sk));
+        Ok(protos)
+    }
 }
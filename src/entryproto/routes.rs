@@ -1,17 +1,20 @@
 use axum::extract::{Path, State};
 use axum::http::StatusCode;
-use axum::routing::{get, put};
+use axum::routing::{get, patch, put};
 use axum::{Json, Router};
 use serde_json::{json, Value};
 
+use crate::entry::EntryStats;
 use crate::{AResult, AppState};
 
-use super::{EntryProto, EntryProtoFC};
+use super::{EntryProto, EntryProtoFC, EntryProtoFU, EntryProtoUpdate};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", put(put_entry_proto))
         .route("/:pk/:sk", get(find))
+        .route("/:sk/stats", get(stats))
+        .route("/:sk", patch(update))
         .route("/active", get(list_active))
         .route("/inactive", get(list_inactive))
         .route("/active/:sk", put(set_as_active))
@@ -20,11 +23,11 @@ pub fn router() -> Router<AppState> {
 
 async fn list_active(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
     let response = EntryProto::ddb_list_active(&state).await?;
-    return Ok((StatusCode::OK, Json(json!(response))));
+    Ok((StatusCode::OK, Json(json!(response))))
 }
 async fn list_inactive(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
     let response = EntryProto::ddb_list_inactive(&state).await?;
-    return Ok((StatusCode::OK, Json(json!(response))));
+    Ok((StatusCode::OK, Json(json!(response))))
 }
 
 async fn set_as_active(
@@ -32,7 +35,7 @@ async fn set_as_active(
     Path(sk): Path<String>,
 ) -> AResult<StatusCode> {
     EntryProto::set_as_active(&state, sk).await?;
-    return Ok(StatusCode::CREATED);
+    Ok(StatusCode::CREATED)
 }
 
 async fn set_as_inactive(
@@ -40,7 +43,19 @@ async fn set_as_inactive(
     Path(sk): Path<String>,
 ) -> AResult<StatusCode> {
     EntryProto::set_as_inactive(&state, sk).await?;
-    return Ok(StatusCode::CREATED);
+    Ok(StatusCode::CREATED)
+}
+
+async fn stats(
+    State(state): State<AppState>,
+    Path(sk): Path<String>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let proto = match EntryProto::ddb_find(&state, "EntryProto::Active", &sk).await {
+        Ok(res) => res,
+        Err(_) => EntryProto::ddb_find(&state, "EntryProto::Inactive", &sk).await?,
+    };
+    let response = EntryStats::ddb_compute(&state, &proto).await?;
+    Ok((StatusCode::OK, Json(json!(response))))
 }
 
 async fn find(
@@ -48,7 +63,7 @@ async fn find(
     Path((pk, sk)): Path<(String, String)>,
 ) -> AResult<(StatusCode, Json<Value>)> {
     let response = EntryProto::ddb_find(&state, pk, sk).await?;
-    return Ok((StatusCode::OK, Json(json!(response))));
+    Ok((StatusCode::OK, Json(json!(response))))
 }
 
 async fn put_entry_proto(
@@ -56,5 +71,22 @@ async fn put_entry_proto(
     Json(payload): Json<EntryProtoFC>,
 ) -> AResult<StatusCode> {
     EntryProto::ddb_put_item(&state, payload).await?;
-    return Ok(StatusCode::CREATED);
+    Ok(StatusCode::CREATED)
+}
+
+async fn update(
+    State(state): State<AppState>,
+    Path(sk): Path<String>,
+    Json(payload): Json<EntryProtoFU>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    match EntryProto::update(&state, sk, payload).await? {
+        EntryProtoUpdate::Updated(proto, updated_entries) => Ok((
+            StatusCode::OK,
+            Json(json!({ "proto": proto, "updated_entries": updated_entries })),
+        )),
+        EntryProtoUpdate::NotFound => Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "message": "EntryProto with given sort key does not exist" })),
+        )),
+    }
 }
@@ -3,14 +3,19 @@ use aws_sdk_dynamodb::Client;
 use axum::http::StatusCode;
 use axum::{routing::get, Router};
 use lambda_http::{run, tracing, Error};
-use std::env::set_var;
+use std::env::{self, set_var};
+use std::sync::Arc;
 
 pub mod archive;
+pub mod attachment;
 pub mod common;
 pub mod entry;
 pub mod entryproto;
 pub mod error;
+pub mod mood;
 pub mod record;
+pub mod recordproto;
+pub mod search;
 pub mod task;
 pub mod taskproto;
 pub mod utils;
@@ -21,15 +26,31 @@ pub use error::{AError, AResult};
 pub struct AppState {
     pub table_name: String,
     pub dynamodb_client: aws_sdk_dynamodb::Client,
+    pub blob_store: Arc<dyn attachment::BlobStore>,
 }
 
 #[tokio::main]
 async fn main() -> std::result::Result<(), Error> {
     let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
 
+    // the Lambda filesystem doesn't outlive the instance, so blobs go to S3 there
+    let blob_store: Arc<dyn attachment::BlobStore> = match env::var("VAULT_BLOB_BUCKET") {
+        Ok(bucket) => Arc::new(attachment::S3BlobStore::new(
+            aws_sdk_s3::Client::new(&config),
+            bucket,
+        )),
+        Err(_) if env::var("AWS_LAMBDA_FUNCTION_NAME").is_ok() => {
+            return Err("VAULT_BLOB_BUCKET must be set when running on Lambda".into());
+        }
+        Err(_) => Arc::new(attachment::LocalBlobStore::new(
+            env::var("VAULT_BLOB_DIR").unwrap_or(String::from("/tmp/vault-blobs")),
+        )),
+    };
+
     let state = AppState {
         table_name: "vault_tasks".to_string(),
         dynamodb_client: Client::new(&config),
+        blob_store,
     };
 
     // If you use API Gateway stages, the Rust Runtime will include the stage name
@@ -50,8 +71,12 @@ async fn main() -> std::result::Result<(), Error> {
         .nest("/api/v1/entry", entry::router())
         .nest("/api/v1/entryproto", entryproto::router())
         .nest("/api/v1/record", record::router())
+        .nest("/api/v1/recordproto", recordproto::router())
         .nest("/api/v1/archive", archive::router())
+        .nest("/api/v1/attachment", attachment::router())
+        .nest("/api/v1/mood", mood::router())
         .nest("/api/v1/common", common::router())
+        .nest("/api/v1/search", search::router())
         .with_state(state);
 
     run(app).await
@@ -0,0 +1,9 @@
+mod model;
+mod routes;
+mod trends;
+
+pub use model::Mood;
+pub use model::MoodFC;
+pub use routes::find_moods_in_window;
+pub use routes::router;
+pub use trends::MoodTrends;
@@ -0,0 +1,132 @@
+use aws_sdk_dynamodb::types::{AttributeValue, ReturnValue};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_dynamo::{from_items, to_item};
+
+use crate::utils::ddb::DdbItem;
+use crate::utils::time::get_date_x_days_ago;
+use crate::{AResult, AppState};
+
+pub const MOOD_PK: &str = "Mood";
+
+const MIN_SCORE: u8 = 1;
+const MAX_SCORE: u8 = 5;
+const MAX_EMOTIONS: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Mood {
+    pub pk: String, // "Mood"
+    pub sk: String, // day of the rating, e.g. "2024-05-01"
+    pub mood: u8,   // 1 (very bad) - 5 (very good)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub energy: Option<u8>, // 1 (exhausted) - 5 (energetic)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emotions: Option<Vec<String>>, // e.g. ["calm", "grateful"]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct MoodFC {
+    pub date: Option<String>, // defaults to today, e.g. "2024-05-01"
+    pub mood: u8,
+    pub energy: Option<u8>,
+    pub emotions: Option<Vec<String>>,
+    pub note: Option<String>,
+}
+
+fn validate_score(name: &str, score: u8) -> Result<(), String> {
+    if !(MIN_SCORE..=MAX_SCORE).contains(&score) {
+        return Err(format!(
+            "{} must be between {} and {}",
+            name, MIN_SCORE, MAX_SCORE
+        ));
+    }
+    Ok(())
+}
+
+impl Mood {
+    /// Validates the rating, the error message is meant for the client.
+    pub fn new(mood_fc: MoodFC) -> Result<Mood, String> {
+        let date = mood_fc.date.unwrap_or(get_date_x_days_ago(0));
+        if NaiveDate::parse_from_str(&date, "%Y-%m-%d").is_err() {
+            return Err(String::from("Mood date must be in YYYY-MM-DD format"));
+        }
+
+        validate_score("mood", mood_fc.mood)?;
+        if let Some(energy) = mood_fc.energy {
+            validate_score("energy", energy)?;
+        }
+
+        let emotions: Vec<String> = mood_fc
+            .emotions
+            .unwrap_or_default()
+            .iter()
+            .map(|e| e.trim().to_lowercase())
+            .filter(|e| !e.is_empty())
+            .collect();
+        if emotions.len() > MAX_EMOTIONS {
+            return Err(format!("Mood can have at most {} emotions", MAX_EMOTIONS));
+        }
+
+        Ok(Mood {
+            pk: String::from(MOOD_PK),
+            sk: date,
+            mood: mood_fc.mood,
+            energy: mood_fc.energy,
+            emotions: (!emotions.is_empty()).then_some(emotions),
+            note: mood_fc.note.filter(|n| !n.trim().is_empty()),
+        })
+    }
+}
+
+// DynamoDB handlers
+impl Mood {
+    /// Creates or replaces the rating of a day.
+    pub async fn ddb_put_item(state: &AppState, mood: &Mood) -> AResult<()> {
+        state
+            .dynamodb_client
+            .put_item()
+            .table_name(&state.table_name)
+            .set_item(Some(to_item(mood)?))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    pub async fn ddb_query_from_to(
+        state: &AppState,
+        from: impl Into<String>,
+        to: impl Into<String>,
+    ) -> AResult<Vec<Mood>> {
+        let items: Vec<DdbItem> = state
+            .dynamodb_client
+            .query()
+            .table_name(&state.table_name)
+            .key_condition_expression("pk = :pk AND sk BETWEEN :from AND :to")
+            .expression_attribute_values(":pk", AttributeValue::S(String::from(MOOD_PK)))
+            .expression_attribute_values(":from", AttributeValue::S(from.into()))
+            .expression_attribute_values(":to", AttributeValue::S(to.into()))
+            .into_paginator()
+            .items()
+            .send()
+            .try_collect()
+            .await?;
+
+        Ok(from_items(items)?)
+    }
+
+    /// Deletes the rating of a day. Returns `false` if there was none.
+    pub async fn ddb_delete(state: &AppState, date: impl Into<String>) -> AResult<bool> {
+        let res = state
+            .dynamodb_client
+            .delete_item()
+            .table_name(&state.table_name)
+            .key("pk", AttributeValue::S(String::from(MOOD_PK)))
+            .key("sk", AttributeValue::S(date.into()))
+            .return_values(ReturnValue::AllOld)
+            .send()
+            .await?;
+        Ok(res.attributes.is_some())
+    }
+}
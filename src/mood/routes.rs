@@ -0,0 +1,101 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::routing::{delete, get, put};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::trends::TrendParams;
+use super::{Mood, MoodFC, MoodTrends};
+use crate::utils::time::{get_today_date, DateWindow};
+use crate::{AResult, AppState};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", put(put_item))
+        .route("/", get(query))
+        .route("/trends", get(trends))
+        .route("/last-week", get(find_last_week_handler))
+        .route("/:date", delete(delete_mood))
+}
+
+#[derive(Deserialize)]
+struct QueryParams {
+    from: String,
+    to: String,
+}
+
+async fn put_item(
+    State(state): State<AppState>,
+    Json(payload): Json<MoodFC>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let mood = match Mood::new(payload) {
+        Ok(mood) => mood,
+        Err(message) => {
+            return Ok((StatusCode::BAD_REQUEST, Json(json!({ "message": message }))));
+        }
+    };
+    Mood::ddb_put_item(&state, &mood).await?;
+    Ok((StatusCode::CREATED, Json(json!(mood))))
+}
+
+async fn query(
+    State(state): State<AppState>,
+    Query(query): Query<QueryParams>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let response = Mood::ddb_query_from_to(&state, &query.from, &query.to).await?;
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "moods": response,
+            "from": query.from,
+            "to": query.to
+        })),
+    ))
+}
+
+async fn trends(
+    State(state): State<AppState>,
+    Query(params): Query<TrendParams>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let range = match params.range(get_today_date()) {
+        Ok(range) => range,
+        Err(message) => {
+            return Ok((StatusCode::BAD_REQUEST, Json(json!({ "message": message }))));
+        }
+    };
+    // the moving averages of the first days also cover ratings from before `from`
+    let moods = Mood::ddb_query_from_to(
+        &state,
+        range.first_rating_day().to_string(),
+        range.to.to_string(),
+    )
+    .await?;
+    let response = MoodTrends::compute(&moods, &range)?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn delete_mood(
+    State(state): State<AppState>,
+    Path(date): Path<String>,
+) -> AResult<StatusCode> {
+    if !Mood::ddb_delete(&state, date).await? {
+        return Ok(StatusCode::NOT_FOUND);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn find_last_week_handler(
+    State(state): State<AppState>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    Ok((
+        StatusCode::OK,
+        Json(json!(
+            find_moods_in_window(&state, &DateWindow::last_days(7, get_today_date())).await?
+        )),
+    ))
+}
+
+pub async fn find_moods_in_window(state: &AppState, window: &DateWindow) -> AResult<Vec<Mood>> {
+    Mood::ddb_query_from_to(state, &window.from, &window.to).await
+}
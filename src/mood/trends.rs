@@ -0,0 +1,265 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use super::Mood;
+use crate::utils::time::{DateWindow, WindowParams};
+use crate::AResult;
+
+const DEFAULT_WINDOW: u32 = 7;
+const MAX_WINDOW: u32 = 90;
+
+#[derive(Deserialize)]
+pub struct TrendParams {
+    pub from: String,        // e.g. "2024-04-01"
+    pub to: String,          // e.g. "2024-04-30"
+    pub window: Option<u32>, // days in moving average, 7 by default
+}
+
+/// Validated TrendParams.
+#[derive(PartialEq, Debug)]
+pub struct TrendRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub window: u32,
+}
+
+impl TrendParams {
+    /// Dates are bounded like the dashboard window, so one request can't read every rating.
+    pub fn range(&self, today: NaiveDate) -> Result<TrendRange, String> {
+        let dates = DateWindow::from_params(
+            &WindowParams {
+                days: None,
+                from: Some(self.from.clone()),
+                to: Some(self.to.clone()),
+            },
+            today,
+        )?;
+        let window = self.window.unwrap_or(DEFAULT_WINDOW);
+        if !(1..=MAX_WINDOW).contains(&window) {
+            return Err(format!("window must be between 1 and {}", MAX_WINDOW));
+        }
+        let parse = |date: &str| date.parse::<NaiveDate>().map_err(|e| e.to_string());
+        Ok(TrendRange {
+            from: parse(&dates.from)?,
+            to: parse(&dates.to)?,
+            window,
+        })
+    }
+}
+
+impl TrendRange {
+    /// First day in the moving average of `from`.
+    pub fn first_rating_day(&self) -> NaiveDate {
+        self.from - Duration::days(self.window as i64 - 1)
+    }
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub struct PeriodAverage {
+    pub days: usize,
+    pub mood: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub energy: Option<f64>,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub struct MovingAverage {
+    pub date: String,
+    pub mood: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub energy: Option<f64>,
+}
+
+#[derive(Serialize)]
+pub struct MoodTrends {
+    pub from: String,
+    pub to: String,
+    pub window: u32,
+    pub weekly: BTreeMap<String, PeriodAverage>, // by ISO week, e.g. "2024-W18"
+    pub monthly: BTreeMap<String, PeriodAverage>, // e.g. "2024-05"
+    pub moving_average: Vec<MovingAverage>,
+    pub emotions: BTreeMap<String, usize>, // how many days each emotion was recorded
+}
+
+fn average(moods: &[&Mood]) -> Option<PeriodAverage> {
+    if moods.is_empty() {
+        return None;
+    }
+    let mood = moods.iter().map(|m| m.mood as f64).sum::<f64>() / moods.len() as f64;
+    let energies: Vec<f64> = moods
+        .iter()
+        .filter_map(|m| m.energy)
+        .map(f64::from)
+        .collect();
+    let energy =
+        (!energies.is_empty()).then(|| energies.iter().sum::<f64>() / energies.len() as f64);
+    Some(PeriodAverage {
+        days: moods.len(),
+        mood,
+        energy,
+    })
+}
+
+impl MoodTrends {
+    /// Computes averages of `moods`, which have to be the ratings from the first rating day
+    /// of `range` to its end. The moving average of a day covers `window` days ending with it
+    /// and is skipped for days with no rating in the window; everything else only covers the
+    /// days from `from` to `to`.
+    pub fn compute(moods: &[Mood], range: &TrendRange) -> AResult<MoodTrends> {
+        let mut by_date: BTreeMap<NaiveDate, &Mood> = BTreeMap::new();
+        for mood in moods {
+            by_date.insert(NaiveDate::parse_from_str(&mood.sk, "%Y-%m-%d")?, mood);
+        }
+
+        let mut weeks: BTreeMap<String, Vec<&Mood>> = BTreeMap::new();
+        let mut months: BTreeMap<String, Vec<&Mood>> = BTreeMap::new();
+        let mut emotions: BTreeMap<String, usize> = BTreeMap::new();
+        for (date, mood) in by_date.range(range.from..=range.to) {
+            let week = date.iso_week();
+            weeks
+                .entry(format!("{}-W{:02}", week.year(), week.week()))
+                .or_default()
+                .push(mood);
+            months
+                .entry(date.format("%Y-%m").to_string())
+                .or_default()
+                .push(mood);
+            for emotion in mood.emotions.iter().flatten() {
+                *emotions.entry(emotion.clone()).or_insert(0) += 1;
+            }
+        }
+
+        let mut moving_average: Vec<MovingAverage> = Vec::new();
+        let mut day = range.from;
+        while day <= range.to {
+            let start = day - Duration::days(range.window as i64 - 1);
+            let in_window: Vec<&Mood> = by_date.range(start..=day).map(|(_, m)| *m).collect();
+            if let Some(avg) = average(&in_window) {
+                moving_average.push(MovingAverage {
+                    date: day.format("%Y-%m-%d").to_string(),
+                    mood: avg.mood,
+                    energy: avg.energy,
+                });
+            }
+            day += Duration::days(1);
+        }
+
+        Ok(MoodTrends {
+            from: range.from.to_string(),
+            to: range.to.to_string(),
+            window: range.window,
+            weekly: weeks
+                .into_iter()
+                .filter_map(|(k, v)| Some((k, average(&v)?)))
+                .collect(),
+            monthly: months
+                .into_iter()
+                .filter_map(|(k, v)| Some((k, average(&v)?)))
+                .collect(),
+            moving_average,
+            emotions,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mood(sk: &str, mood: u8, energy: Option<u8>, emotions: &[&str]) -> Mood {
+        Mood {
+            pk: String::from("Mood"),
+            sk: sk.to_string(),
+            mood,
+            energy,
+            emotions: Some(emotions.iter().map(|e| e.to_string()).collect()),
+            note: None,
+        }
+    }
+
+    fn range(from: &str, to: &str, window: Option<u32>) -> Result<TrendRange, String> {
+        let params = TrendParams {
+            from: from.to_string(),
+            to: to.to_string(),
+            window,
+        };
+        params.range(NaiveDate::from_ymd_opt(2024, 6, 1).unwrap())
+    }
+
+    #[test]
+    fn test_range() {
+        let april = range("2024-04-01", "2024-04-30", None).unwrap();
+        assert_eq!(april.window, 7);
+        assert_eq!(april.first_rating_day().to_string(), "2024-03-26");
+        assert_eq!(
+            range("2024-04-01", "2024-04-30", Some(1))
+                .unwrap()
+                .first_rating_day(),
+            april.from
+        );
+
+        assert!(range("2024-04-31", "2024-05-01", None).is_err());
+        assert!(range("2024-05-01", "2024-04-01", None).is_err());
+        assert!(range("2020-01-01", "2024-04-01", None).is_err());
+        assert!(range("2024-04-01", "2024-04-30", Some(0)).is_err());
+        assert!(range("2024-04-01", "2024-04-30", Some(91)).is_err());
+    }
+
+    #[test]
+    fn test_compute() {
+        let moods = vec![
+            mood("2024-04-28", 4, None, &["tired"]),
+            mood("2024-04-29", 2, Some(3), &["tired"]),
+            mood("2024-04-30", 4, None, &["calm", "tired"]),
+            mood("2024-05-02", 5, Some(5), &[]),
+        ];
+        let range = range("2024-04-29", "2024-05-03", Some(2)).unwrap();
+        let trends = MoodTrends::compute(&moods, &range).unwrap();
+
+        assert_eq!(
+            trends.weekly.get("2024-W18"),
+            Some(&PeriodAverage {
+                days: 3,
+                mood: 11.0 / 3.0,
+                energy: Some(4.0)
+            })
+        );
+        assert_eq!(trends.monthly.get("2024-04").map(|a| a.mood), Some(3.0));
+        assert_eq!(trends.monthly.get("2024-05").map(|a| a.mood), Some(5.0));
+        assert_eq!(trends.emotions.get("tired"), Some(&2));
+
+        let dates: Vec<&str> = trends
+            .moving_average
+            .iter()
+            .map(|m| m.date.as_str())
+            .collect();
+        assert_eq!(
+            dates,
+            vec![
+                "2024-04-29",
+                "2024-04-30",
+                "2024-05-01",
+                "2024-05-02",
+                "2024-05-03"
+            ]
+        );
+        // the rating before `from` only counts in the moving average
+        assert_eq!(trends.weekly.get("2024-W17"), None);
+        assert_eq!(trends.moving_average[0].mood, 3.0);
+        assert_eq!(trends.moving_average[1].mood, 3.0);
+        assert_eq!(trends.moving_average[1].energy, Some(3.0));
+        assert_eq!(trends.moving_average[2].mood, 4.0);
+        assert_eq!(trends.moving_average[2].energy, None);
+    }
+
+    #[test]
+    fn test_compute_skips_days_without_ratings() {
+        let moods = vec![mood("2024-05-10", 3, None, &[])];
+        let range = range("2024-05-01", "2024-05-12", Some(1)).unwrap();
+        let trends = MoodTrends::compute(&moods, &range).unwrap();
+        assert_eq!(trends.moving_average.len(), 1);
+        assert_eq!(trends.moving_average[0].date, "2024-05-10");
+    }
+}
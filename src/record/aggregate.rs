@@ -0,0 +1,294 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::units::find_unit;
+use super::Record;
+use crate::recordproto::normalize_record_name;
+use crate::utils::id::id_timestamp;
+use crate::utils::time::TIMEZONE;
+use crate::AResult;
+
+// averages are rounded, the other functions are exact
+const AVERAGE_DP: u32 = 6;
+const MAX_BUCKETS: usize = 3660;
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Bucket {
+    #[default]
+    Day,
+    Week, // ISO week, starting on Monday
+    Month,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AggregateFn {
+    #[default]
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Count,
+}
+
+#[derive(Deserialize)]
+pub struct AggregateParams {
+    pub name: String,
+    pub from: String, // inclusive, e.g. "2024-05-01"
+    pub to: String,   // inclusive, e.g. "2024-05-31"
+    #[serde(default)]
+    pub bucket: Bucket,
+    #[serde(default, rename = "fn")]
+    pub function: AggregateFn,
+    pub unit: Option<String>, // unit of the values, the unit of the latest record by default
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+pub struct BucketValue {
+    pub start: String, // first day of the bucket, e.g. "2024-04-29" for week 2024-W18
+    pub count: usize,
+    pub value: Option<Decimal>, // `None` for an empty bucket, except for sum and count
+}
+
+#[derive(Serialize)]
+pub struct Aggregation {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+    pub bucket: Bucket,
+    #[serde(rename = "fn")]
+    pub function: AggregateFn,
+    pub unit: Option<String>,
+    pub skipped: usize, // records whose unit can't be converted to `unit`
+    pub series: Vec<BucketValue>,
+}
+
+pub fn parse_date(date: &str) -> AResult<NaiveDate> {
+    Ok(NaiveDate::parse_from_str(date, "%Y-%m-%d")?)
+}
+
+/// Day of a record in the configured timezone. Sort keys are RFC 3339 timestamps, but a plain
+/// date is accepted as well.
+pub fn record_date(record: &Record) -> Option<NaiveDate> {
+    let timestamp = id_timestamp(&record.sk);
+    match DateTime::parse_from_rfc3339(timestamp) {
+        Ok(datetime) => Some(datetime.with_timezone(&TIMEZONE).date_naive()),
+        Err(_) => parse_date(timestamp.get(..10)?).ok(),
+    }
+}
+
+fn bucket_start(date: NaiveDate, bucket: Bucket) -> NaiveDate {
+    match bucket {
+        Bucket::Day => date,
+        Bucket::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        Bucket::Month => date.with_day(1).unwrap_or(date),
+    }
+}
+
+fn next_bucket_start(start: NaiveDate, bucket: Bucket) -> NaiveDate {
+    match bucket {
+        Bucket::Day => start + Duration::days(1),
+        Bucket::Week => start + Duration::days(7),
+        Bucket::Month => {
+            let (year, month) = match start.month() {
+                12 => (start.year() + 1, 1),
+                m => (start.year(), m + 1),
+            };
+            NaiveDate::from_ymd_opt(year, month, 1).unwrap_or(start)
+        }
+    }
+}
+
+fn apply(function: AggregateFn, values: &[Decimal]) -> Option<Decimal> {
+    match function {
+        AggregateFn::Sum => Some(values.iter().sum()),
+        AggregateFn::Count => Some(Decimal::from(values.len())),
+        _ if values.is_empty() => None,
+        AggregateFn::Avg => {
+            let sum: Decimal = values.iter().sum();
+            Some(
+                (sum / Decimal::from(values.len()))
+                    .round_dp(AVERAGE_DP)
+                    .normalize(),
+            )
+        }
+        AggregateFn::Min => values.iter().min().copied(),
+        AggregateFn::Max => values.iter().max().copied(),
+    }
+}
+
+impl Aggregation {
+    /// Aggregates `records` named `params.name` into consecutive buckets between `params.from`
+    /// and `params.to`, including buckets without any record.
+    pub fn compute(params: &AggregateParams, records: Vec<Record>) -> AResult<Aggregation> {
+        let (from, to) = (parse_date(&params.from)?, parse_date(&params.to)?);
+        if from > to {
+            return Err(anyhow::Error::msg("Aggregation 'from' must not be after 'to'").into());
+        }
+
+        let name = normalize_record_name(&params.name);
+        let mut records: Vec<Record> = records
+            .into_iter()
+            .filter(|r| normalize_record_name(&r.name) == name)
+            .collect();
+        records.sort_by(|a, b| a.sk.cmp(&b.sk));
+
+        let unit = match &params.unit {
+            Some(name) => Some(
+                find_unit(name)
+                    .ok_or(anyhow::Error::msg(format!("Unknown unit '{}'", name)))?
+                    .symbol
+                    .to_string(),
+            ),
+            None => records.iter().rev().find_map(|r| r.unit.clone()),
+        };
+
+        let mut buckets: BTreeMap<NaiveDate, Vec<Decimal>> = BTreeMap::new();
+        let mut start = bucket_start(from, params.bucket);
+        while start <= to {
+            if buckets.len() >= MAX_BUCKETS {
+                return Err(anyhow::Error::msg("Too many buckets, use a larger bucket").into());
+            }
+            buckets.insert(start, Vec::new());
+            start = next_bucket_start(start, params.bucket);
+        }
+
+        let mut skipped = 0;
+        for mut record in records {
+            let Some(date) = record_date(&record).filter(|d| (from..=to).contains(d)) else {
+                continue;
+            };
+            let convertible = match (unit.as_deref().and_then(find_unit), &record.unit) {
+                (Some(target), Some(_)) => record.convert_to(target),
+                _ => record.unit == unit,
+            };
+            if !convertible {
+                skipped += 1;
+                continue;
+            }
+            if let Some(values) = buckets.get_mut(&bucket_start(date, params.bucket)) {
+                values.push(record.amount);
+            }
+        }
+
+        Ok(Aggregation {
+            name: params.name.clone(),
+            from: params.from.clone(),
+            to: params.to.clone(),
+            bucket: params.bucket,
+            function: params.function,
+            unit,
+            skipped,
+            series: buckets
+                .into_iter()
+                .map(|(start, values)| BucketValue {
+                    start: start.format("%Y-%m-%d").to_string(),
+                    count: values.len(),
+                    value: apply(params.function, &values),
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn record(sk: &str, name: &str, amount: &str, unit: Option<&str>) -> Record {
+        Record {
+            pk: String::from("Record"),
+            sk: sk.to_string(),
+            name: name.to_string(),
+            amount: Decimal::from_str(amount).unwrap(),
+            unit: unit.map(String::from),
+            version: 0,
+            record_month: String::new(),
+        }
+    }
+
+    fn params(bucket: Bucket, function: AggregateFn, unit: Option<&str>) -> AggregateParams {
+        AggregateParams {
+            name: String::from("water"),
+            from: String::from("2024-04-29"),
+            to: String::from("2024-05-02"),
+            bucket,
+            function,
+            unit: unit.map(String::from),
+        }
+    }
+
+    fn records() -> Vec<Record> {
+        vec![
+            record("2024-04-29T08:00:00+02:00", "water", "500", Some("ml")),
+            record("2024-04-29T20:00:00+02:00", "water", "1.5", Some("l")),
+            // 23:30 UTC is already the next day in Warsaw
+            record("2024-04-30T23:30:00Z", "water", "250", Some("ml")),
+            record("2024-04-30T12:00:00+02:00", "water", "2", Some("kg")),
+            record("2024-04-30T12:00:00+02:00", "weight", "72.4", Some("kg")),
+        ]
+    }
+
+    fn values(aggregation: &Aggregation) -> Vec<(&str, Option<String>)> {
+        aggregation
+            .series
+            .iter()
+            .map(|b| (b.start.as_str(), b.value.map(|v| v.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn test_daily_sum_fills_empty_days() {
+        let agg =
+            Aggregation::compute(&params(Bucket::Day, AggregateFn::Sum, None), records()).unwrap();
+        assert_eq!(agg.unit.as_deref(), Some("ml"));
+        assert_eq!(agg.skipped, 1);
+        assert_eq!(
+            values(&agg),
+            vec![
+                ("2024-04-29", Some(String::from("2000"))),
+                ("2024-04-30", Some(String::from("0"))),
+                ("2024-05-01", Some(String::from("250"))),
+                ("2024-05-02", Some(String::from("0"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_functions_and_buckets() {
+        let agg = |bucket, function| {
+            Aggregation::compute(&params(bucket, function, Some("L")), records()).unwrap()
+        };
+        assert_eq!(
+            agg(Bucket::Day, AggregateFn::Avg).series[0]
+                .value
+                .unwrap()
+                .to_string(),
+            "1"
+        );
+        assert_eq!(agg(Bucket::Day, AggregateFn::Avg).series[1].value, None);
+        assert_eq!(
+            values(&agg(Bucket::Week, AggregateFn::Max)),
+            vec![("2024-04-29", Some(String::from("1.5")))]
+        );
+        assert_eq!(
+            values(&agg(Bucket::Month, AggregateFn::Count)),
+            vec![
+                ("2024-04-01", Some(String::from("2"))),
+                ("2024-05-01", Some(String::from("1")))
+            ]
+        );
+        assert_eq!(
+            agg(Bucket::Month, AggregateFn::Min).series[1]
+                .value
+                .unwrap()
+                .to_string(),
+            "0.25"
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::{DateTime, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_dynamo::to_item;
+
+use super::{Record, RecordFC};
+use crate::recordproto::{normalize_record_name, RecordProto};
+use crate::utils::ddb::ddb_put_new_all;
+use crate::utils::id::id_at;
+use crate::utils::time::TIMEZONE;
+use crate::{AResult, AppState};
+
+const MAX_IMPORT_ROWS: usize = 10_000;
+const PREVIEW_ROWS: usize = 20;
+
+// accepted besides RFC 3339; dates without time are imported at midnight
+const DATETIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+];
+const DATE_FORMATS: [&str; 3] = ["%Y-%m-%d", "%d.%m.%Y", "%d/%m/%Y"];
+
+/// CSV column header for each record attribute. `name` and `unit` can instead be given once
+/// for the whole file, e.g. a spreadsheet of body weight has no name column.
+#[derive(Deserialize)]
+pub struct ColumnMapping {
+    pub timestamp: String,
+    pub amount: String,
+    pub name: Option<String>,
+    pub unit: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct RecordImport {
+    pub csv: String, // with a header row
+    pub mapping: ColumnMapping,
+    pub timezone: Option<String>, // of timestamps without offset, e.g. "Europe/London"
+    pub name: Option<String>,     // used when there is no name column
+    pub unit: Option<String>,     // used when there is no unit column or the cell is empty
+    #[serde(default)]
+    pub delimiter: Option<char>, // "," by default
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct RowError {
+    pub row: usize, // line number in the file, the header is line 1
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub total_rows: usize,
+    pub valid_rows: usize,
+    pub imported: usize,
+    pub errors: Vec<RowError>,
+    pub preview: Vec<Record>, // first valid records as they will be stored
+}
+
+fn parse_timestamp(value: &str, tz: Tz) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.with_timezone(&Utc));
+    }
+
+    let naive = DATETIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(value, f).ok())
+        .or_else(|| {
+            DATE_FORMATS
+                .iter()
+                .find_map(|f| NaiveDate::parse_from_str(value, f).ok())
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+        .ok_or(format!("Invalid timestamp '{}'", value))?;
+
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => {
+            Ok(datetime.with_timezone(&Utc))
+        }
+        LocalResult::None => Err(format!("Timestamp '{}' does not exist in {}", value, tz)),
+    }
+}
+
+// spreadsheets often use a decimal comma, e.g. "72,4"
+fn parse_amount(value: &str) -> Result<Decimal, String> {
+    let value = value.trim();
+    let normalized = match value.contains('.') {
+        true => value.to_string(),
+        false => value.replace(',', "."),
+    };
+    Decimal::from_str(&normalized).map_err(|_| format!("Invalid amount '{}'", value))
+}
+
+/// A row parsed into a record that doesn't yet follow its RecordProto.
+pub struct ParsedRow {
+    pub row: usize,
+    pub record: Record,
+}
+
+/// Parses every row, collecting errors instead of stopping at the first one.
+pub fn parse_rows(import: &RecordImport) -> AResult<(Vec<ParsedRow>, Vec<RowError>)> {
+    let tz: Tz = match &import.timezone {
+        Some(tz) => tz
+            .parse()
+            .map_err(|_| anyhow::Error::msg(format!("Unknown timezone '{}'", tz)))?,
+        None => TIMEZONE,
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(import.delimiter.unwrap_or(',') as u8)
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(import.csv.as_bytes());
+
+    let headers = reader.headers()?.clone();
+    let column = |header: &str| -> AResult<usize> {
+        headers
+            .iter()
+            .position(|h| h == header)
+            .ok_or(anyhow::Error::msg(format!("Column '{}' not found", header)).into())
+    };
+    let mapping = &import.mapping;
+    let timestamp_col = column(&mapping.timestamp)?;
+    let amount_col = column(&mapping.amount)?;
+    let name_col = mapping.name.as_deref().map(column).transpose()?;
+    let unit_col = mapping.unit.as_deref().map(column).transpose()?;
+    if name_col.is_none() && import.name.is_none() {
+        return Err(anyhow::Error::msg("Either a name column or a name is required").into());
+    }
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for (i, result) in reader.records().enumerate() {
+        let row = i + 2;
+        if i >= MAX_IMPORT_ROWS {
+            return Err(anyhow::Error::msg(format!(
+                "CSV must not have more than {} rows",
+                MAX_IMPORT_ROWS
+            ))
+            .into());
+        }
+        let parsed = result.map_err(|e| e.to_string()).and_then(|cells| {
+            let cell = |col: Option<usize>| {
+                col.and_then(|c| cells.get(c))
+                    .filter(|v| !v.is_empty())
+                    .map(String::from)
+            };
+            let name = cell(name_col)
+                .or(import.name.clone())
+                .ok_or(String::from("Missing name"))?;
+            let amount = parse_amount(&cell(Some(amount_col)).unwrap_or_default())?;
+            // readings of the same time, e.g. several on one date, get keys of their own
+            let sk = id_at(parse_timestamp(
+                &cell(Some(timestamp_col)).unwrap_or_default(),
+                tz,
+            )?);
+
+            let mut record = Record::new(RecordFC {
+                name,
+                amount,
+                unit: cell(unit_col).or(import.unit.clone()),
+            })
+            .map_err(|e| e.0.to_string())?;
+            record.sk = sk;
+            Ok(record)
+        });
+        match parsed {
+            Ok(record) => rows.push(ParsedRow { row, record }),
+            Err(message) => errors.push(RowError { row, message }),
+        }
+    }
+    Ok((rows, errors))
+}
+
+impl ImportReport {
+    /// Validates rows against active RecordProtos and, unless it is a dry run, writes the
+    /// valid ones. Invalid rows are reported and skipped.
+    pub async fn ddb_import(state: &AppState, import: RecordImport) -> AResult<ImportReport> {
+        let (rows, mut errors) = parse_rows(&import)?;
+        let total_rows = rows.len() + errors.len();
+
+        let mut protos: HashMap<String, Result<RecordProto, String>> = HashMap::new();
+        let mut rows_of_records = Vec::new();
+        let mut records = Vec::new();
+        for ParsedRow { row, record } in rows {
+            let name = normalize_record_name(&record.name);
+            if !protos.contains_key(&name) {
+                let proto = RecordProto::find_active_for(state, &name)
+                    .await
+                    .map_err(|e| e.0.to_string());
+                protos.insert(name.clone(), proto);
+            }
+            let conformed = match &protos[&name] {
+                Ok(proto) => record.conform_to(proto).map_err(|e| e.0.to_string()),
+                Err(message) => Err(message.clone()),
+            };
+            let record = match conformed {
+                Ok(record) => record.keyed(),
+                Err(message) => {
+                    errors.push(RowError { row, message });
+                    continue;
+                }
+            };
+            rows_of_records.push(row);
+            records.push(record);
+        }
+
+        let valid_rows = records.len();
+        let mut imported = 0;
+        if !import.dry_run {
+            let items = records.iter().map(to_item).collect::<Result<Vec<_>, _>>()?;
+            let taken = ddb_put_new_all(state, items).await?;
+            for &i in &taken {
+                errors.push(RowError {
+                    row: rows_of_records[i],
+                    message: String::from("A record with the same key already exists"),
+                });
+            }
+            imported = valid_rows - taken.len();
+        }
+        errors.sort_by_key(|e| e.row);
+
+        records.truncate(PREVIEW_ROWS);
+        Ok(ImportReport {
+            dry_run: import.dry_run,
+            total_rows,
+            valid_rows,
+            imported,
+            errors,
+            preview: records,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::id::id_timestamp;
+    use chrono::SecondsFormat;
+
+    fn import(csv: &str, mapping: &str) -> RecordImport {
+        let mut import: RecordImport = serde_json::from_str(&format!(
+            r#"{{"csv": "", "mapping": {}, "timezone": "Europe/London"}}"#,
+            mapping
+        ))
+        .unwrap();
+        import.csv = csv.to_string();
+        import
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        let london: Tz = "Europe/London".parse().unwrap();
+        let local = |value: &str| {
+            parse_timestamp(value, london)
+                .unwrap()
+                .with_timezone(&TIMEZONE)
+                .to_rfc3339_opts(SecondsFormat::Secs, true)
+        };
+        assert_eq!(local("2024-05-01 07:30"), "2024-05-01T08:30:00+02:00");
+        assert_eq!(local("01.01.2024"), "2024-01-01T01:00:00+01:00");
+        assert_eq!(local("2024-05-01T06:30:00Z"), "2024-05-01T08:30:00+02:00");
+        // skipped by the switch to summer time
+        assert!(parse_timestamp("2024-03-31 01:30", london).is_err());
+        assert!(parse_timestamp("yesterday", london).is_err());
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let csv = "Date,What,Value,Unit\n\
+            2024-05-01 07:30,Water,\"0,5\",L\n\
+            2024-05-01,Water,abc,ml\n\
+            not a date,Water,250,ml\n\
+            2024-05-02,,250,ml\n\
+            2024-05-03,Water,-1,ml\n";
+        let (rows, errors) = parse_rows(&import(
+            csv,
+            r#"{"timestamp": "Date", "name": "What", "amount": "Value", "unit": "Unit"}"#,
+        ))
+        .unwrap();
+
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].row, 2);
+        assert_eq!(
+            id_timestamp(&rows[0].record.sk),
+            "2024-05-01T08:30:00+02:00"
+        );
+        assert_eq!(rows[0].record.amount, Decimal::from_str("0.5").unwrap());
+        assert_eq!(rows[0].record.unit.as_deref(), Some("l"));
+        let rows_with_errors: Vec<usize> = errors.iter().map(|e| e.row).collect();
+        assert_eq!(rows_with_errors, vec![3, 4, 5, 6]);
+        assert_eq!(errors[0].message, "Invalid amount 'abc'");
+        assert_eq!(errors[2].message, "Missing name");
+    }
+
+    #[test]
+    fn test_parse_rows_with_defaults() {
+        let mut weight = import(
+            "day;kg\n2024-05-01;72,4\n",
+            r#"{"timestamp": "day", "amount": "kg"}"#,
+        );
+        weight.delimiter = Some(';');
+        assert!(parse_rows(&weight).is_err());
+
+        weight.name = Some(String::from("weight"));
+        weight.unit = Some(String::from("kg"));
+        let (rows, errors) = parse_rows(&weight).unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(rows[0].record.name, "weight");
+        assert_eq!(rows[0].record.amount.to_string(), "72.4");
+
+        let missing = import("a,b\n1,2\n", r#"{"timestamp": "day", "amount": "kg"}"#);
+        assert!(parse_rows(&missing).is_err());
+    }
+
+    #[test]
+    fn test_parse_rows_of_same_date() {
+        let mut pressure = import(
+            "day,mmHg\n2024-05-01,120\n2024-05-01,118\n",
+            r#"{"timestamp": "day", "amount": "mmHg"}"#,
+        );
+        pressure.name = Some(String::from("pressure"));
+        let (rows, errors) = parse_rows(&pressure).unwrap();
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            id_timestamp(&rows[0].record.sk),
+            id_timestamp(&rows[1].record.sk)
+        );
+        assert_ne!(rows[0].record.sk, rows[1].record.sk);
+    }
+}
@@ -1,7 +1,19 @@
+mod aggregate;
+mod import;
 mod model;
+mod partition;
 mod routes;
+mod targets;
+mod units;
 
+pub use aggregate::{AggregateFn, Aggregation};
+pub use import::{ImportReport, RecordImport};
+pub use model::normalize_unit;
 pub use model::Record;
 pub use model::RecordFC;
-pub use routes::find_last_week_records;
+pub use model::{RecordFU, RecordUpdate};
+pub use partition::{LEGACY_RECORD_PK, RECORD_MONTH_INDEX};
+pub use routes::find_records_in_window;
 pub use routes::router;
+pub use targets::{TargetReport, TargetStatus};
+pub use units::{convert, find_unit, Dimension, Unit, UNITS};
@@ -1,114 +1,609 @@
-use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::types::{AttributeValue, Delete, Put, TransactWriteItem};
+use chrono::{DateTime, SecondsFormat};
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use serde_dynamo::{from_items, to_item};
 
-use crate::{utils::time::get_today_datetime, AResult, AppState};
+use super::partition::{
+    months_between, record_month, record_pk, LEGACY_RECORD_PK, RECORD_MONTH_INDEX,
+};
+use super::units::{convert, find_unit, Unit};
+use crate::recordproto::{normalize_record_name, RecordProto};
+use crate::utils::ddb::{ddb_key, ddb_put_new, DdbItem};
+use crate::utils::id::new_id;
+use crate::utils::time::TIMEZONE;
+use crate::{AResult, AppState};
+
+// condition of writes based on a read record; records never updated have no version yet
+const UNCHANGED_VERSION: &str =
+    "attribute_exists(pk) AND (attribute_not_exists(version) OR version = :version)";
 
 #[derive(Serialize, Deserialize)]
 pub struct Record {
     pub pk: String,
     pub sk: String,
     pub name: String,
-    pub amount: u64,
+    pub amount: Decimal, // a string to stay exact, e.g. "72.4"; numbers are accepted too
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub unit: Option<String>,
+    pub unit: Option<String>, // canonical symbol of a known unit, e.g. "kg", or free-form text
+    #[serde(default)]
+    pub version: u64, // incremented on every update, missing in records never updated
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub record_month: String, // partition key in RECORD_MONTH_INDEX, e.g. "Record::2024-05"
 }
 
 #[derive(Deserialize)]
 pub struct RecordFC {
     pub name: String,
-    pub amount: u64,
+    pub amount: Decimal,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>, // e.g. "L", "litres" and "l" are all stored as "l"
+}
+
+#[derive(Deserialize, Default)]
+pub struct RecordFU {
+    pub name: Option<String>,
+    #[serde(default)]
+    pub amount: Option<Decimal>,
     pub unit: Option<String>,
+    pub timestamp: Option<String>, // new sort key in RFC 3339, e.g. "2024-05-01T08:30:00+02:00"
+    pub version: Option<u64>, // version the edit is based on, rejected if the record changed since
+}
+
+pub enum RecordUpdate {
+    Updated(Record),
+    NotFound,
+    Conflict,
+}
+
+/// Known units are stored by their canonical symbol, unknown ones are only trimmed.
+pub fn normalize_unit(unit: Option<String>) -> Option<String> {
+    let unit = unit?;
+    match find_unit(&unit) {
+        Some(known) => Some(known.symbol.to_string()),
+        None => Some(unit.trim().to_string()).filter(|u| !u.is_empty()),
+    }
 }
 
-impl From<RecordFC> for Record {
-    fn from(record: RecordFC) -> Self {
-        Record {
-            pk: String::from("Record"),
-            sk: get_today_datetime(),
+impl Record {
+    pub fn new(record: RecordFC) -> AResult<Record> {
+        if record.amount.is_sign_negative() {
+            return Err(anyhow::Error::msg("Record amount must not be negative").into());
+        }
+        Ok(Record {
+            pk: String::new(),
+            sk: new_id(),
             name: record.name,
-            amount: record.amount,
-            unit: record.unit,
+            amount: record.amount.normalize(),
+            unit: normalize_unit(record.unit),
+            version: 0,
+            record_month: String::new(),
+        }
+        .keyed())
+    }
+
+    /// Sets the keys derived from the name and the timestamp, see `record_pk`.
+    pub fn keyed(mut self) -> Record {
+        self.pk = record_pk(&self.name, &self.sk);
+        self.record_month = record_month(&self.sk);
+        self
+    }
+
+    /// Makes the record follow its RecordProto: the canonical name, the unit of the
+    /// RecordProto (converting the amount if needed) and the min/max bounds.
+    pub fn conform_to(mut self, proto: &RecordProto) -> AResult<Record> {
+        self.name = proto.name.clone();
+        match (self.unit.clone(), &proto.unit) {
+            (None, unit) => self.unit = unit.clone(),
+            (Some(unit), Some(proto_unit)) if &unit == proto_unit => {}
+            (Some(unit), Some(proto_unit)) => {
+                let converted = find_unit(proto_unit).is_some_and(|u| self.convert_to(u));
+                if !converted {
+                    return Err(anyhow::Error::msg(format!(
+                        "Unit '{}' can't be used for '{}', use '{}'",
+                        unit, proto.name, proto_unit
+                    ))
+                    .into());
+                }
+            }
+            (Some(unit), None) => {
+                return Err(anyhow::Error::msg(format!(
+                    "Record '{}' has no unit, got '{}'",
+                    proto.name, unit
+                ))
+                .into());
+            }
+        }
+
+        if proto.min.is_some_and(|min| self.amount < min)
+            || proto.max.is_some_and(|max| self.amount > max)
+        {
+            return Err(anyhow::Error::msg(format!(
+                "Amount of '{}' must be between {} and {}",
+                proto.name,
+                proto.min.map_or(String::from("-"), |m| m.to_string()),
+                proto.max.map_or(String::from("-"), |m| m.to_string()),
+            ))
+            .into());
+        }
+        Ok(self)
+    }
+
+    /// Applies changes to a copy of the record. The result still has to conform to the
+    /// RecordProto of its (possibly new) name.
+    pub fn apply(&self, record_fu: &RecordFU) -> AResult<Record> {
+        let amount = record_fu.amount.unwrap_or(self.amount);
+        if amount.is_sign_negative() {
+            return Err(anyhow::Error::msg("Record amount must not be negative").into());
+        }
+        let sk = match &record_fu.timestamp {
+            Some(timestamp) => DateTime::parse_from_rfc3339(timestamp)
+                .map_err(|_| anyhow::Error::msg("Record timestamp must be in RFC 3339 format"))?
+                .with_timezone(&TIMEZONE)
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            None => self.sk.clone(),
+        };
+        Ok(Record {
+            pk: self.pk.clone(),
+            sk,
+            name: record_fu.name.clone().unwrap_or(self.name.clone()),
+            amount: amount.normalize(),
+            unit: match &record_fu.unit {
+                Some(unit) => normalize_unit(Some(unit.clone())),
+                None => self.unit.clone(),
+            },
+            version: self.version + 1,
+            record_month: self.record_month.clone(),
+        })
+    }
+
+    /// Converts the amount to `unit` if the record has a known unit of the same dimension.
+    /// Returns `false` and leaves the record unchanged otherwise.
+    pub fn convert_to(&mut self, unit: &Unit) -> bool {
+        let Some(from) = self.unit.as_deref().and_then(find_unit) else {
+            return false;
+        };
+        match convert(self.amount, from, unit) {
+            Some(amount) => {
+                self.amount = amount;
+                self.unit = Some(unit.symbol.to_string());
+                true
+            }
+            None => false,
         }
     }
 }
 
 impl Record {
-    pub async fn ddb_create(state: &AppState, record_fc: RecordFC) -> AResult<()> {
-        let item = to_item(Into::<Record>::into(record_fc))?;
+    /// Rejects records whose name has no active RecordProto.
+    pub async fn ddb_create(state: &AppState, record_fc: RecordFC) -> AResult<Record> {
+        let proto = RecordProto::find_active_for(state, &record_fc.name).await?;
+        let record = Record::new(record_fc)?.conform_to(&proto)?.keyed();
+        ddb_put_new(state, to_item(&record)?).await?;
+        Ok(record)
+    }
 
-        let req = state
-            .dynamodb_client
-            .put_item()
-            .table_name(&state.table_name)
-            .set_item(Some(item));
+    /// Finds a record by its timestamp. Records of different names can share a timestamp,
+    /// in which case `name` is required to tell them apart.
+    pub async fn ddb_find(
+        state: &AppState,
+        sk: impl Into<String>,
+        name: Option<&str>,
+    ) -> AResult<Option<Record>> {
+        let sk = sk.into();
+        let mut found = Record::ddb_query_range(
+            state,
+            Some(RECORD_MONTH_INDEX),
+            ("record_month", record_month(&sk)),
+            &sk,
+            &sk,
+        )
+        .await?;
+        found.extend(
+            Record::ddb_query_range(state, None, ("pk", LEGACY_RECORD_PK.to_string()), &sk, &sk)
+                .await?,
+        );
+        if let Some(name) = name {
+            let name = normalize_record_name(name);
+            found.retain(|r| normalize_record_name(&r.name) == name);
+        }
 
-        req.send().await?;
-        Ok(())
+        match found.len() {
+            0 | 1 => Ok(found.pop()),
+            _ => Err(anyhow::Error::msg(
+                "Several records have this timestamp, specify the record name",
+            )
+            .into()),
+        }
+    }
+
+    /// Updates a record if it hasn't changed since it was read (or since `record_fu.version`).
+    /// A new name or timestamp moves the record to new keys in a single transaction.
+    pub async fn ddb_update(
+        state: &AppState,
+        sk: impl Into<String>,
+        name: Option<&str>,
+        record_fu: RecordFU,
+    ) -> AResult<RecordUpdate> {
+        let Some(record) = Record::ddb_find(state, sk, name).await? else {
+            return Ok(RecordUpdate::NotFound);
+        };
+        if record_fu.version.is_some_and(|v| v != record.version) {
+            return Ok(RecordUpdate::Conflict);
+        }
+
+        let updated = record.apply(&record_fu)?;
+        let proto = RecordProto::find_active_for(state, &updated.name).await?;
+        let updated = updated.conform_to(&proto)?.keyed();
+
+        let version = AttributeValue::N(record.version.to_string());
+
+        if (&updated.pk, &updated.sk) == (&record.pk, &record.sk) {
+            let res = state
+                .dynamodb_client
+                .put_item()
+                .table_name(&state.table_name)
+                .set_item(Some(to_item(&updated)?))
+                .condition_expression(UNCHANGED_VERSION)
+                .expression_attribute_values(":version", version)
+                .send()
+                .await;
+            return match res {
+                Err(err)
+                    if err
+                        .as_service_error()
+                        .is_some_and(|e| e.is_conditional_check_failed_exception()) =>
+                {
+                    Ok(RecordUpdate::Conflict)
+                }
+                Err(err) => Err(err.into()),
+                Ok(_) => Ok(RecordUpdate::Updated(updated)),
+            };
+        }
+
+        let put = Put::builder()
+            .table_name(&state.table_name)
+            .set_item(Some(to_item(&updated)?))
+            .condition_expression("attribute_not_exists(pk)")
+            .build()?;
+        let delete = Delete::builder()
+            .table_name(&state.table_name)
+            .set_key(Some(ddb_key(&record.pk, &record.sk)))
+            .condition_expression(UNCHANGED_VERSION)
+            .expression_attribute_values(":version", version)
+            .build()?;
+        let res = state
+            .dynamodb_client
+            .transact_write_items()
+            .transact_items(TransactWriteItem::builder().put(put).build())
+            .transact_items(TransactWriteItem::builder().delete(delete).build())
+            .send()
+            .await;
+        match res {
+            Err(err)
+                if err
+                    .as_service_error()
+                    .is_some_and(|e| e.is_transaction_canceled_exception()) =>
+            {
+                Ok(RecordUpdate::Conflict)
+            }
+            Err(err) => Err(err.into()),
+            Ok(_) => Ok(RecordUpdate::Updated(updated)),
+        }
     }
 
-    pub async fn ddb_delete(state: &AppState, sk: impl Into<String>) -> AResult<()> {
+    pub async fn ddb_delete(state: &AppState, record: &Record) -> AResult<()> {
         let req = state
             .dynamodb_client
             .delete_item()
             .table_name(&state.table_name)
-            .key("pk", AttributeValue::S(String::from("Record")))
-            .key("sk", AttributeValue::S(sk.into()));
+            .key("pk", AttributeValue::S(record.pk.clone()))
+            .key("sk", AttributeValue::S(record.sk.clone()));
 
         req.send().await?;
         Ok(())
     }
 
-    pub async fn ddb_query_from_to(
+    // all records of one partition (of the table or of `index`) with sort key in from..=to
+    async fn ddb_query_range(
         state: &AppState,
-        from: impl Into<String>,
-        to: impl Into<String>,
+        index: Option<&str>,
+        (key_name, key): (&str, String),
+        from: &str,
+        to: &str,
     ) -> AResult<Vec<Record>> {
-        let res = state
+        let items: Vec<DdbItem> = state
             .dynamodb_client
-            .execute_statement()
-            .statement(format!(
-                r#"SELECT * FROM "{}" WHERE "pk" = ? AND "sk" >= ? AND "sk" <= ?"#,
-                state.table_name
-            ))
-            .set_parameters(Some(vec![
-                AttributeValue::S("Record".to_string()),
-                AttributeValue::S(from.into()),
-                AttributeValue::S(to.into()),
-            ]))
+            .query()
+            .table_name(&state.table_name)
+            .set_index_name(index.map(String::from))
+            .key_condition_expression("#key = :key AND sk BETWEEN :from AND :to")
+            .expression_attribute_names("#key", key_name)
+            .expression_attribute_values(":key", AttributeValue::S(key))
+            .expression_attribute_values(":from", AttributeValue::S(from.to_string()))
+            .expression_attribute_values(":to", AttributeValue::S(to.to_string()))
+            .into_paginator()
+            .items()
             .send()
+            .try_collect()
             .await?;
+        Ok(from_items(items)?)
+    }
 
-        match res.items {
-            Some(items) => {
-                let records: Vec<Record> = from_items(items)?;
-                return Ok(records);
-            }
-            None => {
-                return Err(anyhow::Error::msg("Error querying DynamoDB Records. {:?}").into());
-            }
+    /// Records of every name with sort key between `from` and `to` (inclusive), oldest first.
+    /// Reads one index partition per month, plus records not migrated yet.
+    pub async fn ddb_query_from_to(
+        state: &AppState,
+        from: impl Into<String>,
+        to: impl Into<String>,
+    ) -> AResult<Vec<Record>> {
+        let (from, to) = (from.into(), to.into());
+        if from > to {
+            return Ok(Vec::new());
+        }
+        let mut records = Vec::new();
+        for month in months_between(&from, &to).map_err(anyhow::Error::msg)? {
+            let key = ("record_month", record_month(&month));
+            records.extend(
+                Record::ddb_query_range(state, Some(RECORD_MONTH_INDEX), key, &from, &to).await?,
+            );
+        }
+        let legacy = ("pk", LEGACY_RECORD_PK.to_string());
+        records.extend(Record::ddb_query_range(state, None, legacy, &from, &to).await?);
+        Ok(dedup_records(records))
+    }
+
+    /// Records of one name with sort key between `from` and `to` (inclusive), oldest first.
+    pub async fn ddb_query_name_from_to(
+        state: &AppState,
+        name: &str,
+        from: impl Into<String>,
+        to: impl Into<String>,
+    ) -> AResult<Vec<Record>> {
+        let (from, to) = (from.into(), to.into());
+        if from > to {
+            return Ok(Vec::new());
+        }
+        let name = normalize_record_name(name);
+        let mut records = Vec::new();
+        for month in months_between(&from, &to).map_err(anyhow::Error::msg)? {
+            let key = ("pk", record_pk(&name, &month));
+            records.extend(Record::ddb_query_range(state, None, key, &from, &to).await?);
         }
+        let legacy = ("pk", LEGACY_RECORD_PK.to_string());
+        let mut legacy = Record::ddb_query_range(state, None, legacy, &from, &to).await?;
+        legacy.retain(|r| normalize_record_name(&r.name) == name);
+        records.extend(legacy);
+        Ok(dedup_records(records))
     }
 
-    pub async fn ddb_query(state: &AppState, sk: impl Into<String>) -> AResult<Vec<Record>> {
-        let query = state
+    /// Moves up to `limit` records from the legacy "Record" partition to partitions by name
+    /// and month. Safe to run while the API is used and to repeat after a failure: queries
+    /// read both layouts until the legacy partition is empty. Each record is copied and
+    /// deleted in one transaction, and only if its new key is free and it wasn't edited
+    /// meanwhile. Returns the number of moved records, the sort keys of skipped ones and
+    /// whether any are left.
+    pub async fn ddb_migrate_legacy(
+        state: &AppState,
+        limit: i32,
+    ) -> AResult<(usize, Vec<String>, bool)> {
+        let res = state
             .dynamodb_client
             .query()
             .table_name(&state.table_name)
-            .key_condition_expression("pk = :pk AND sk >= :sk")
-            .expression_attribute_values(":pk", AttributeValue::S(String::from("Record")))
-            .expression_attribute_values(":sk", AttributeValue::S(sk.into()));
-
-        let res = query.send().await?;
-        match res.items {
-            Some(items) => {
-                let records: Vec<Record> = from_items(items)?;
-                return Ok(records);
-            }
-            None => {
-                return Err(anyhow::Error::msg("Error querying DynamoDB Records. {:?}").into());
+            .key_condition_expression("pk = :pk")
+            .expression_attribute_values(":pk", AttributeValue::S(LEGACY_RECORD_PK.to_string()))
+            .limit(limit)
+            .send()
+            .await?;
+        let remaining = res.last_evaluated_key.is_some();
+        let records: Vec<Record> = from_items(res.items.unwrap_or_default())?;
+
+        let mut migrated = 0;
+        let mut skipped = Vec::new();
+        for mut record in records {
+            let legacy_key = ddb_key(&record.pk, &record.sk);
+            let version = AttributeValue::N(record.version.to_string());
+            record.name = normalize_record_name(&record.name);
+            let record = record.keyed();
+
+            let put = Put::builder()
+                .table_name(&state.table_name)
+                .set_item(Some(to_item(&record)?))
+                .condition_expression("attribute_not_exists(pk)")
+                .build()?;
+            let delete = Delete::builder()
+                .table_name(&state.table_name)
+                .set_key(Some(legacy_key))
+                .condition_expression(UNCHANGED_VERSION)
+                .expression_attribute_values(":version", version)
+                .build()?;
+            let res = state
+                .dynamodb_client
+                .transact_write_items()
+                .transact_items(TransactWriteItem::builder().put(put).build())
+                .transact_items(TransactWriteItem::builder().delete(delete).build())
+                .send()
+                .await;
+            match res {
+                // moved or edited by a PATCH meanwhile, or its new key is taken
+                Err(err)
+                    if err
+                        .as_service_error()
+                        .is_some_and(|e| e.is_transaction_canceled_exception()) =>
+                {
+                    skipped.push(record.sk)
+                }
+                Err(err) => return Err(err.into()),
+                Ok(_) => migrated += 1,
             }
         }
+        Ok((migrated, skipped, remaining))
+    }
+}
+
+// a record read from both layouts in the middle of its migration is returned once
+fn dedup_records(records: Vec<Record>) -> Vec<Record> {
+    let mut keyed: Vec<(String, Record)> = records
+        .into_iter()
+        .map(|r| (normalize_record_name(&r.name), r))
+        .collect();
+    keyed.sort_by(|(a_name, a), (b_name, b)| (&a.sk, a_name).cmp(&(&b.sk, b_name)));
+    keyed.dedup_by(|(a_name, a), (b_name, b)| a.sk == b.sk && a_name == b_name);
+    keyed.into_iter().map(|(_, r)| r).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn record(amount: &str, unit: Option<&str>) -> Record {
+        Record::new(RecordFC {
+            name: String::from("Water"),
+            amount: Decimal::from_str(amount).unwrap(),
+            unit: unit.map(String::from),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_new_normalizes_unit() {
+        assert_eq!(record("1.50", Some(" Litres ")).unit.as_deref(), Some("l"));
+        assert_eq!(record("1.50", Some("l")).amount.to_string(), "1.5");
+        assert_eq!(record("3", Some(" kcal ")).unit.as_deref(), Some("kcal"));
+        assert_eq!(record("3", Some(" ")).unit, None);
+        assert!(Record::new(RecordFC {
+            name: String::from("Water"),
+            amount: Decimal::from_str("-1").unwrap(),
+            unit: None,
+        })
+        .is_err());
+    }
+
+    fn proto(json: &str) -> RecordProto {
+        RecordProto::new(serde_json::from_str(json).unwrap(), "RecordProto::Active").unwrap()
+    }
+
+    #[test]
+    fn test_conform_to() {
+        let water = proto(r#"{"name": "Water", "unit": "ml", "max": 5000}"#);
+        let res = record("1.5", Some("L")).conform_to(&water).unwrap();
+        assert_eq!(res.name, "water");
+        assert_eq!(
+            (res.amount, res.unit.as_deref()),
+            (Decimal::from(1500), Some("ml"))
+        );
+        assert_eq!(
+            record("250", None)
+                .conform_to(&water)
+                .unwrap()
+                .unit
+                .as_deref(),
+            Some("ml")
+        );
+        assert!(record("6", Some("l")).conform_to(&water).is_err());
+        assert!(record("1", Some("kg")).conform_to(&water).is_err());
+        assert!(record("1", Some("glass")).conform_to(&water).is_err());
+
+        let pushups = proto(r#"{"name": "pushups", "min": 1}"#);
+        assert!(record("20", None).conform_to(&pushups).is_ok());
+        assert!(record("20", Some("pcs")).conform_to(&pushups).is_err());
+        assert!(record("0", None).conform_to(&pushups).is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let water = record("1.5", Some("l"));
+        let res = water
+            .apply(&RecordFU {
+                amount: Some(Decimal::from(2)),
+                timestamp: Some(String::from("2024-05-01T06:30:00Z")),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(res.sk, "2024-05-01T08:30:00+02:00");
+        assert_eq!(
+            (res.amount, res.unit.as_deref()),
+            (Decimal::from(2), Some("l"))
+        );
+        assert_eq!(res.version, 1);
+
+        let res = water
+            .apply(&RecordFU {
+                name: Some(String::from("Juice")),
+                unit: Some(String::from("ML")),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            (res.name.as_str(), res.unit.as_deref()),
+            ("Juice", Some("ml"))
+        );
+        assert_eq!(res.sk, water.sk);
+
+        let invalid = |fu: RecordFU| water.apply(&fu).is_err();
+        assert!(invalid(RecordFU {
+            timestamp: Some(String::from("2024-05-01")),
+            ..Default::default()
+        }));
+        assert!(invalid(RecordFU {
+            amount: Some(Decimal::from(-1)),
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn test_keyed_and_dedup() {
+        let mut water = record("1", Some("l"));
+        water.sk = String::from("2024-05-01T08:30:00+02:00");
+        let water = water.keyed();
+        assert_eq!(water.pk, "Record::Water::2024-05");
+        assert_eq!(water.record_month, "Record::2024-05");
+
+        let mut legacy = record("1", Some("l"));
+        legacy.sk = water.sk.clone();
+        legacy.pk = String::from(LEGACY_RECORD_PK);
+        let mut other = record("2", Some("l"));
+        other.name = String::from("juice");
+        other.sk = water.sk.clone();
+
+        let records = dedup_records(vec![legacy, other, water]);
+        let names: Vec<&str> = records.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["juice", "Water"]);
+    }
+
+    #[test]
+    fn test_convert_to() {
+        let ml = find_unit("ml").unwrap();
+        let mut water = record("1.5", Some("l"));
+        assert!(water.convert_to(ml));
+        assert_eq!(
+            (water.amount.to_string(), water.unit),
+            ("1500".to_string(), Some("ml".to_string()))
+        );
+
+        let mut meal = record("600", Some("kcal"));
+        assert!(!meal.convert_to(ml));
+        assert_eq!(meal.unit.as_deref(), Some("kcal"));
+        assert!(!record("1", Some("kg")).convert_to(ml));
+    }
+
+    #[test]
+    fn test_dynamodb_roundtrip() {
+        let item: DdbItem = to_item(record("72.4", Some("kg"))).unwrap();
+        assert_eq!(item["amount"], AttributeValue::S(String::from("72.4")));
+        let back: Record = serde_dynamo::from_item(item).unwrap();
+        assert_eq!(back.amount, Decimal::from_str("72.4").unwrap());
+
+        let precise: DdbItem = to_item(record("0.1000000000000000055511151231", None)).unwrap();
+        let back: Record = serde_dynamo::from_item(precise).unwrap();
+        assert_eq!(back.amount.to_string(), "0.1000000000000000055511151231");
+
+        // amounts written as numbers
+        let mut legacy: DdbItem = to_item(record("1", None)).unwrap();
+        legacy.insert(String::from("amount"), AttributeValue::N(String::from("3")));
+        let back: Record = serde_dynamo::from_item(legacy).unwrap();
+        assert_eq!(back.amount, Decimal::from(3));
     }
 }
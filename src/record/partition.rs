@@ -0,0 +1,98 @@
+use chrono::{Datelike, NaiveDate};
+
+/// Partition of records written before they were split by name and month.
+pub const LEGACY_RECORD_PK: &str = "Record";
+
+/// Global secondary index for date queries across record names.
+/// Partition key: `record_month` (S), sort key: `sk` (S), projection: ALL.
+pub const RECORD_MONTH_INDEX: &str = "record_month-sk-index";
+
+const MAX_MONTHS: usize = 1200;
+
+// sort keys are local RFC 3339 timestamps, so their first 7 characters are the local month
+fn month_of(sk: &str) -> &str {
+    sk.get(..7).unwrap_or(sk)
+}
+
+/// Partition key of a record, e.g. "Record::water::2024-05".
+pub fn record_pk(name: &str, sk: &str) -> String {
+    format!("Record::{}::{}", name, month_of(sk))
+}
+
+/// Partition key of a record in `RECORD_MONTH_INDEX`, e.g. "Record::2024-05".
+pub fn record_month(sk: &str) -> String {
+    format!("Record::{}", month_of(sk))
+}
+
+/// Months touched by the sort key range `from..=to`, e.g. ["2024-04", "2024-05"]. Both
+/// bounds must start with a month, e.g. "2024-05", "2024-05-01" or a full timestamp.
+pub fn months_between(from: &str, to: &str) -> Result<Vec<String>, String> {
+    let parse = |s: &str| {
+        NaiveDate::parse_from_str(&format!("{}-01", month_of(s)), "%Y-%m-%d")
+            .map_err(|_| format!("Invalid range bound '{}', expected YYYY-MM-DD", s))
+    };
+    let (mut month, last) = (parse(from)?, parse(to)?);
+
+    let mut months = Vec::new();
+    while month <= last {
+        if months.len() >= MAX_MONTHS {
+            return Err(String::from("Record query range is too long"));
+        }
+        months.push(month.format("%Y-%m").to_string());
+        month = match month.month() {
+            12 => NaiveDate::from_ymd_opt(month.year() + 1, 1, 1),
+            m => NaiveDate::from_ymd_opt(month.year(), m + 1, 1),
+        }
+        .ok_or(String::from("Invalid record query range"))?;
+    }
+    Ok(months)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys() {
+        let sk = "2024-05-01T08:30:00+02:00";
+        assert_eq!(record_pk("water", sk), "Record::water::2024-05");
+        assert_eq!(record_month(sk), "Record::2024-05");
+    }
+
+    #[test]
+    fn test_months_between() {
+        assert_eq!(
+            months_between("2023-11-30", "2024-02-01T10:00:00+01:00").unwrap(),
+            vec!["2023-11", "2023-12", "2024-01", "2024-02"]
+        );
+        assert_eq!(
+            months_between("2024-05-01", "2024-05-31").unwrap(),
+            vec!["2024-05"]
+        );
+        assert!(months_between("2024-05-01", "2024-04-30")
+            .unwrap()
+            .is_empty());
+        assert!(months_between("last week", "2024-05-01").is_err());
+    }
+
+    #[test]
+    fn test_months_between_query_shapes() {
+        // bounds accepted by GET /record before records were split by month
+        assert_eq!(
+            months_between("2024-05", "2024-05-31~").unwrap(),
+            vec!["2024-05"]
+        );
+        assert_eq!(
+            months_between("2024-04-30T23:00:00+02:00", "2024-05-01T00:00:00Z").unwrap(),
+            vec!["2024-04", "2024-05"]
+        );
+        assert_eq!(
+            months_between("2024", "2024-05-01"),
+            Err(String::from(
+                "Invalid range bound '2024', expected YYYY-MM-DD"
+            ))
+        );
+        assert!(months_between("", "2024-05-01").is_err());
+        assert!(months_between("2024-05-01", "2024-13-01").is_err());
+    }
+}
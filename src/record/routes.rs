@@ -1,72 +1,198 @@
 use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
-use axum::routing::{delete, get, post};
+use axum::routing::{delete, get, patch, post};
 use axum::{Json, Router};
+use chrono::Duration;
 use serde::Deserialize;
 use serde_json::{json, Value};
 
-use super::{Record, RecordFC};
-use crate::utils::time::get_date_x_days_ago;
+use super::aggregate::{parse_date, AggregateParams};
+use super::partition::months_between;
+use super::{
+    find_unit, Aggregation, ImportReport, Record, RecordFC, RecordFU, RecordImport, RecordUpdate,
+    TargetReport, UNITS,
+};
+use crate::utils::time::{get_today_date, DateWindow};
 use crate::{AResult, AppState};
 
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", post(create))
-        .route("/:sk", delete(delete_task))
+        .route("/:sk", delete(delete_record))
+        .route("/:sk", patch(update))
         .route("/last-week", get(find_last_week_handler))
         .route("/", get(query))
+        .route("/units", get(list_units))
+        .route("/aggregate", get(aggregate))
+        .route("/migrate", post(migrate))
+        .route("/import", post(import))
+        .route("/targets/status", get(targets_status))
+}
+
+// records of different names can share a timestamp
+#[derive(Deserialize)]
+struct NameParams {
+    name: Option<String>,
+}
+
+const DEFAULT_MIGRATION_BATCH: i32 = 500;
+
+#[derive(Deserialize)]
+struct MigrateParams {
+    limit: Option<i32>,
 }
 
 #[derive(Deserialize)]
 struct QueryParams {
     from: String,
     to: String,
+    unit: Option<String>, // convert amounts to this unit where the dimension matches, e.g. "ml"
 }
 
 async fn query(
     State(state): State<AppState>,
     Query(query): Query<QueryParams>,
 ) -> AResult<(StatusCode, Json<Value>)> {
-    let response = Record::ddb_query_from_to(&state, &query.from, &query.to).await?;
-    return Ok((
+    if let Err(message) = months_between(&query.from, &query.to) {
+        return Ok((StatusCode::BAD_REQUEST, Json(json!({ "message": message }))));
+    }
+    let mut response = Record::ddb_query_from_to(&state, &query.from, &query.to).await?;
+    if let Some(name) = &query.unit {
+        let Some(unit) = find_unit(name) else {
+            return Ok((
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "message": format!("Unknown unit '{}'", name) })),
+            ));
+        };
+        for record in response.iter_mut() {
+            record.convert_to(unit);
+        }
+    }
+    Ok((
         StatusCode::OK,
         Json(json!({
             "records": response,
             "from": query.from,
             "to": query.to
         })),
-    ));
+    ))
+}
+
+async fn aggregate(
+    State(state): State<AppState>,
+    Query(params): Query<AggregateParams>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    // sort keys are timestamps, so records of the last day are below the following date;
+    // one extra day on both sides covers records whose local day differs from their UTC one
+    let from = (parse_date(&params.from)? - Duration::days(1)).to_string();
+    let to = (parse_date(&params.to)? + Duration::days(2)).to_string();
+    let records = Record::ddb_query_name_from_to(&state, &params.name, from, to).await?;
+    let response = Aggregation::compute(&params, records)?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn list_units() -> (StatusCode, Json<Value>) {
+    let units: Vec<Value> = UNITS
+        .iter()
+        .map(|u| {
+            json!({
+                "symbol": u.symbol,
+                "aliases": u.aliases,
+                "dimension": u.dimension,
+                "factor": u.factor(),
+            })
+        })
+        .collect();
+    (StatusCode::OK, Json(json!(units)))
 }
 
 async fn create(
     State(state): State<AppState>,
     Json(payload): Json<RecordFC>,
-) -> AResult<StatusCode> {
-    Record::ddb_create(&state, payload).await?;
-    Ok(StatusCode::CREATED)
+) -> AResult<(StatusCode, Json<Value>)> {
+    let record = Record::ddb_create(&state, payload).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "pk": record.pk, "sk": record.sk })),
+    ))
 }
 
-async fn delete_task(State(state): State<AppState>, Path(sk): Path<String>) -> AResult<StatusCode> {
-    let query_res = Record::ddb_query(&state, &sk).await?;
+async fn update(
+    State(state): State<AppState>,
+    Path(sk): Path<String>,
+    Query(params): Query<NameParams>,
+    Json(payload): Json<RecordFU>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    match Record::ddb_update(&state, sk, params.name.as_deref(), payload).await? {
+        RecordUpdate::Updated(record) => Ok((StatusCode::OK, Json(json!(record)))),
+        RecordUpdate::NotFound => Ok((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "message": "Record with provided sort key does not exist" })),
+        )),
+        RecordUpdate::Conflict => Ok((
+            StatusCode::CONFLICT,
+            Json(json!({ "message": "Record was changed by another request, fetch it and retry" })),
+        )),
+    }
+}
 
-    if query_res.is_empty() {
+async fn delete_record(
+    State(state): State<AppState>,
+    Path(sk): Path<String>,
+    Query(params): Query<NameParams>,
+) -> AResult<StatusCode> {
+    let Some(record) = Record::ddb_find(&state, sk, params.name.as_deref()).await? else {
         return Ok(StatusCode::NOT_FOUND);
-    }
+    };
 
-    Record::ddb_delete(&state, sk).await?;
+    Record::ddb_delete(&state, &record).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
+async fn targets_status(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
+    let response = TargetReport::ddb_compute_all(&state).await?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn import(
+    State(state): State<AppState>,
+    Json(payload): Json<RecordImport>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let dry_run = payload.dry_run;
+    let response = ImportReport::ddb_import(&state, payload).await?;
+    let status = match dry_run {
+        true => StatusCode::OK,
+        false => StatusCode::CREATED,
+    };
+    Ok((status, Json(json!(response))))
+}
+
+async fn migrate(
+    State(state): State<AppState>,
+    Query(params): Query<MigrateParams>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_MIGRATION_BATCH)
+        .clamp(1, 1000);
+    let (migrated, skipped, remaining) = Record::ddb_migrate_legacy(&state, limit).await?;
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "migrated": migrated, "skipped": skipped, "remaining": remaining })),
+    ))
+}
+
 async fn find_last_week_handler(
     State(state): State<AppState>,
 ) -> AResult<(StatusCode, Json<Value>)> {
     return Ok((
         StatusCode::OK,
-        Json(json!(find_last_week_records(&state).await?)),
+        Json(json!(
+            find_records_in_window(&state, &DateWindow::last_days(7, get_today_date())).await?
+        )),
     ));
 }
 
-pub async fn find_last_week_records(state: &AppState) -> AResult<Vec<Record>> {
-    let response = Record::ddb_query(&state, get_date_x_days_ago(7)).await?;
-    Ok(response)
+pub async fn find_records_in_window(state: &AppState, window: &DateWindow) -> AResult<Vec<Record>> {
+    Record::ddb_query_from_to(state, &window.from, window.sk_to()).await
 }
@@ -0,0 +1,274 @@
+use chrono::{Datelike, Duration, NaiveDate};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use super::aggregate::{AggregateFn, AggregateParams, Aggregation, Bucket};
+use super::Record;
+use crate::recordproto::{RecordProto, RecordTarget, TargetOp, TargetPeriod};
+use crate::utils::time::get_date_x_days_ago;
+use crate::{AResult, AppState};
+
+// how far back hit streaks are counted
+const DAILY_LOOKBACK_DAYS: i64 = 365;
+const WEEKLY_LOOKBACK_WEEKS: i64 = 104;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetStatus {
+    Met,
+    Missed,
+    InProgress,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PeriodStatus {
+    pub start: String, // first day of the period, e.g. "2024-04-29"
+    pub value: Option<Decimal>,
+    pub status: TargetStatus,
+}
+
+#[derive(Serialize)]
+pub struct TargetReport {
+    pub name: String,
+    pub unit: Option<String>,
+    pub target: RecordTarget,
+    pub status: TargetStatus, // of today for daily targets, of this week for weekly ones
+    pub this_week: Vec<PeriodStatus>, // days since Monday for daily targets, the week otherwise
+    pub streak: u32,          // periods met in a row, the current one counts once it is met
+}
+
+fn satisfies(target: &RecordTarget, value: Decimal) -> bool {
+    match target.op {
+        TargetOp::Gte => value >= target.amount,
+        TargetOp::Lte => value <= target.amount,
+    }
+}
+
+/// Status of a period with the aggregated `value`. A period that is not over yet is only
+/// decided early when further records can't change the outcome, e.g. a sum that already
+/// reached a ">=" target.
+fn period_status(target: &RecordTarget, value: Option<Decimal>, complete: bool) -> TargetStatus {
+    let Some(value) = value else {
+        return match complete {
+            true => TargetStatus::Missed,
+            false => TargetStatus::InProgress,
+        };
+    };
+    let satisfied = satisfies(target, value);
+    if complete {
+        return match satisfied {
+            true => TargetStatus::Met,
+            false => TargetStatus::Missed,
+        };
+    }
+
+    // sum, count and max only grow with more records, min only shrinks
+    let grows = matches!(
+        target.function,
+        AggregateFn::Sum | AggregateFn::Count | AggregateFn::Max
+    );
+    let shrinks = target.function == AggregateFn::Min;
+    match (target.op, satisfied) {
+        (TargetOp::Gte, true) if grows => TargetStatus::Met,
+        (TargetOp::Lte, false) if grows => TargetStatus::Missed,
+        (TargetOp::Lte, true) if shrinks => TargetStatus::Met,
+        (TargetOp::Gte, false) if shrinks => TargetStatus::Missed,
+        _ => TargetStatus::InProgress,
+    }
+}
+
+impl TargetReport {
+    /// Returns `None` if the RecordProto has no target.
+    pub fn compute(
+        proto: &RecordProto,
+        records: Vec<Record>,
+        today: NaiveDate,
+    ) -> AResult<Option<TargetReport>> {
+        let Some(target) = &proto.target else {
+            return Ok(None);
+        };
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let (bucket, from) = match target.period {
+            TargetPeriod::Daily => (Bucket::Day, today - Duration::days(DAILY_LOOKBACK_DAYS)),
+            TargetPeriod::Weekly => (
+                Bucket::Week,
+                monday - Duration::weeks(WEEKLY_LOOKBACK_WEEKS),
+            ),
+        };
+
+        let params = AggregateParams {
+            name: proto.name.clone(),
+            from: from.format("%Y-%m-%d").to_string(),
+            to: today.format("%Y-%m-%d").to_string(),
+            bucket,
+            function: target.function,
+            unit: None, // records are stored in the unit of their RecordProto
+        };
+        let series = Aggregation::compute(&params, records)?.series;
+
+        // the last bucket is the one containing today
+        let last = series.len().saturating_sub(1);
+        let periods: Vec<PeriodStatus> = series
+            .into_iter()
+            .enumerate()
+            .map(|(i, b)| PeriodStatus {
+                status: period_status(target, b.value, i < last),
+                start: b.start,
+                value: b.value,
+            })
+            .collect();
+
+        let Some(current) = periods.last().map(|p| p.status) else {
+            return Err(anyhow::Error::msg("Target has no periods to report").into());
+        };
+        let streak = periods[..last]
+            .iter()
+            .rev()
+            .take_while(|p| p.status == TargetStatus::Met)
+            .count() as u32
+            + (current == TargetStatus::Met) as u32;
+
+        let this_week_start = monday.format("%Y-%m-%d").to_string();
+        let this_week: Vec<PeriodStatus> = periods
+            .into_iter()
+            .filter(|p| p.start >= this_week_start)
+            .collect();
+
+        Ok(Some(TargetReport {
+            name: proto.name.clone(),
+            unit: proto.unit.clone(),
+            target: target.clone(),
+            status: current,
+            this_week,
+            streak,
+        }))
+    }
+
+    pub async fn ddb_compute_all(state: &AppState) -> AResult<Vec<TargetReport>> {
+        let today = NaiveDate::parse_from_str(&get_date_x_days_ago(0), "%Y-%m-%d")?;
+        let from = today - Duration::days(DAILY_LOOKBACK_DAYS.max(7 * WEEKLY_LOOKBACK_WEEKS + 7));
+        let to = format!("{}~", today.format("%Y-%m-%d"));
+
+        let mut reports = Vec::new();
+        for proto in RecordProto::ddb_list_active(state).await? {
+            if proto.target.is_none() {
+                continue;
+            }
+            let records =
+                Record::ddb_query_name_from_to(state, &proto.name, from.to_string(), &to).await?;
+            reports.extend(TargetReport::compute(&proto, records, today)?);
+        }
+        Ok(reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn proto(json: &str) -> RecordProto {
+        RecordProto::new(serde_json::from_str(json).unwrap(), "RecordProto::Active").unwrap()
+    }
+
+    fn record(sk: &str, name: &str, amount: &str) -> Record {
+        Record {
+            pk: String::new(),
+            sk: sk.to_string(),
+            name: name.to_string(),
+            amount: Decimal::from_str(amount).unwrap(),
+            unit: None,
+            version: 0,
+            record_month: String::new(),
+        }
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_period_status() {
+        let target: RecordTarget =
+            serde_json::from_str(r#"{"period": "daily", "amount": 2000}"#).unwrap();
+        let value = |v: i64| Some(Decimal::from(v));
+        assert_eq!(
+            period_status(&target, value(2000), false),
+            TargetStatus::Met
+        );
+        assert_eq!(
+            period_status(&target, value(1000), false),
+            TargetStatus::InProgress
+        );
+        assert_eq!(
+            period_status(&target, value(1000), true),
+            TargetStatus::Missed
+        );
+
+        let target: RecordTarget =
+            serde_json::from_str(r#"{"period": "weekly", "amount": 80, "op": "lte", "fn": "avg"}"#)
+                .unwrap();
+        assert_eq!(
+            period_status(&target, value(85), false),
+            TargetStatus::InProgress
+        );
+        assert_eq!(period_status(&target, value(79), true), TargetStatus::Met);
+        assert_eq!(period_status(&target, None, true), TargetStatus::Missed);
+    }
+
+    #[test]
+    fn test_daily_target() {
+        let water = proto(r#"{"name": "water", "target": {"period": "daily", "amount": 2000}}"#);
+        let records = vec![
+            record("2024-04-28T10:00:00+02:00", "water", "2500"),
+            record("2024-04-29T10:00:00+02:00", "water", "1500"),
+            record("2024-04-29T18:00:00+02:00", "water", "500"),
+            record("2024-04-30T10:00:00+02:00", "water", "2000"),
+            record("2024-05-01T10:00:00+02:00", "water", "1000"),
+        ];
+        let report = TargetReport::compute(&water, records, date("2024-05-01"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.status, TargetStatus::InProgress);
+        assert_eq!(report.streak, 3);
+        let week: Vec<(&str, TargetStatus)> = report
+            .this_week
+            .iter()
+            .map(|p| (p.start.as_str(), p.status))
+            .collect();
+        assert_eq!(
+            week,
+            vec![
+                ("2024-04-29", TargetStatus::Met),
+                ("2024-04-30", TargetStatus::Met),
+                ("2024-05-01", TargetStatus::InProgress),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_weekly_average_target() {
+        let weight = proto(
+            r#"{"name": "weight", "unit": "kg",
+                "target": {"period": "weekly", "amount": 80, "op": "lte", "fn": "avg"}}"#,
+        );
+        let records = vec![
+            record("2024-04-16T07:00:00+02:00", "weight", "79.5"),
+            record("2024-04-23T07:00:00+02:00", "weight", "81"),
+            record("2024-04-25T07:00:00+02:00", "weight", "78"),
+            record("2024-04-30T07:00:00+02:00", "weight", "79"),
+        ];
+        let report = TargetReport::compute(&weight, records, date("2024-05-01"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(report.status, TargetStatus::InProgress);
+        assert_eq!(report.this_week.len(), 1);
+        assert_eq!(report.streak, 2);
+
+        assert!(
+            TargetReport::compute(&proto(r#"{"name": "water"}"#), vec![], date("2024-05-01"))
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+// digits kept after converting between units, e.g. 1 oz = 28.349523 g
+const CONVERSION_DP: u32 = 6;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Dimension {
+    Mass,     // base unit: g
+    Volume,   // base unit: ml
+    Distance, // base unit: m
+    Time,     // base unit: s
+    Count,    // base unit: pcs
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Unit {
+    pub symbol: &'static str, // canonical spelling stored in Records
+    pub aliases: &'static [&'static str],
+    pub dimension: Dimension,
+    factor: (i64, u32), // size in the base unit of the dimension, as a Decimal mantissa and scale
+}
+
+impl Unit {
+    pub fn factor(&self) -> Decimal {
+        Decimal::new(self.factor.0, self.factor.1)
+    }
+}
+
+const fn unit(
+    symbol: &'static str,
+    aliases: &'static [&'static str],
+    dimension: Dimension,
+    factor: (i64, u32),
+) -> Unit {
+    Unit {
+        symbol,
+        aliases,
+        dimension,
+        factor,
+    }
+}
+
+pub const UNITS: [Unit; 22] = [
+    unit("mg", &["milligram", "milligrams"], Dimension::Mass, (1, 3)),
+    unit("g", &["gram", "grams"], Dimension::Mass, (1, 0)),
+    unit(
+        "kg",
+        &["kilogram", "kilograms", "kgs"],
+        Dimension::Mass,
+        (1000, 0),
+    ),
+    unit(
+        "oz",
+        &["ounce", "ounces"],
+        Dimension::Mass,
+        (28349523125, 9),
+    ),
+    unit(
+        "lb",
+        &["lbs", "pound", "pounds"],
+        Dimension::Mass,
+        (45359237, 5),
+    ),
+    unit(
+        "ml",
+        &["millilitre", "milliliter", "millilitres", "milliliters"],
+        Dimension::Volume,
+        (1, 0),
+    ),
+    unit(
+        "cl",
+        &["centilitre", "centiliter"],
+        Dimension::Volume,
+        (10, 0),
+    ),
+    unit(
+        "dl",
+        &["decilitre", "deciliter"],
+        Dimension::Volume,
+        (100, 0),
+    ),
+    unit(
+        "l",
+        &["litre", "liter", "litres", "liters"],
+        Dimension::Volume,
+        (1000, 0),
+    ),
+    unit(
+        "mm",
+        &["millimetre", "millimeter", "millimetres", "millimeters"],
+        Dimension::Distance,
+        (1, 3),
+    ),
+    unit(
+        "cm",
+        &["centimetre", "centimeter", "centimetres", "centimeters"],
+        Dimension::Distance,
+        (1, 2),
+    ),
+    unit(
+        "m",
+        &["metre", "meter", "metres", "meters"],
+        Dimension::Distance,
+        (1, 0),
+    ),
+    unit(
+        "km",
+        &["kilometre", "kilometer", "kilometres", "kilometers"],
+        Dimension::Distance,
+        (1000, 0),
+    ),
+    unit("mi", &["mile", "miles"], Dimension::Distance, (1609344, 3)),
+    unit(
+        "ms",
+        &["millisecond", "milliseconds"],
+        Dimension::Time,
+        (1, 3),
+    ),
+    unit("s", &["sec", "second", "seconds"], Dimension::Time, (1, 0)),
+    unit(
+        "min",
+        &["mins", "minute", "minutes"],
+        Dimension::Time,
+        (60, 0),
+    ),
+    unit(
+        "h",
+        &["hr", "hrs", "hour", "hours"],
+        Dimension::Time,
+        (3600, 0),
+    ),
+    unit("d", &["day", "days"], Dimension::Time, (86400, 0)),
+    unit(
+        "pcs",
+        &["pc", "piece", "pieces", "x", "times"],
+        Dimension::Count,
+        (1, 0),
+    ),
+    unit("pair", &["pairs"], Dimension::Count, (2, 0)),
+    unit("dozen", &["dozens"], Dimension::Count, (12, 0)),
+];
+
+/// Finds a unit by its symbol or alias, ignoring case and surrounding whitespace.
+pub fn find_unit(name: &str) -> Option<&'static Unit> {
+    let name = name.trim().to_lowercase();
+    UNITS
+        .iter()
+        .find(|u| u.symbol == name || u.aliases.contains(&name.as_str()))
+}
+
+/// Converts `amount` between units of the same dimension.
+pub fn convert(amount: Decimal, from: &Unit, to: &Unit) -> Option<Decimal> {
+    if from.dimension != to.dimension {
+        return None;
+    }
+    let converted = amount
+        .checked_mul(from.factor())?
+        .checked_div(to.factor())?;
+    Some(converted.round_dp(CONVERSION_DP).normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_find_unit() {
+        assert_eq!(find_unit(" L ").map(|u| u.symbol), Some("l"));
+        assert_eq!(find_unit("Kilograms").map(|u| u.symbol), Some("kg"));
+        assert_eq!(find_unit("kcal"), None);
+
+        let mut names: Vec<&str> = UNITS
+            .iter()
+            .flat_map(|u| u.aliases.iter().copied().chain([u.symbol]))
+            .collect();
+        let all = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), all, "unit names must be unique");
+    }
+
+    #[test]
+    fn test_convert() {
+        let unit = |name: &str| find_unit(name).unwrap();
+        assert_eq!(
+            convert(dec("1.5"), unit("l"), unit("ml")),
+            Some(dec("1500"))
+        );
+        assert_eq!(
+            convert(dec("250"), unit("ml"), unit("l")),
+            Some(dec("0.25"))
+        );
+        assert_eq!(
+            convert(dec("72.4"), unit("kg"), unit("lb")),
+            Some(dec("159.614678"))
+        );
+        assert_eq!(convert(dec("90"), unit("min"), unit("h")), Some(dec("1.5")));
+        assert_eq!(
+            convert(dec("1"), unit("dozen"), unit("pcs")),
+            Some(dec("12"))
+        );
+        assert_eq!(convert(dec("1"), unit("kg"), unit("l")), None);
+    }
+}
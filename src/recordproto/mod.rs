@@ -0,0 +1,7 @@
+mod model;
+mod routes;
+
+pub use model::RecordProto;
+pub use model::RecordProtoFC;
+pub use model::{normalize_record_name, RecordTarget, TargetOp, TargetPeriod};
+pub use routes::router;
@@ -0,0 +1,357 @@
+use aws_sdk_dynamodb::types::AttributeValue;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_dynamo::{from_item, from_items, to_item};
+
+use crate::record::{normalize_unit, AggregateFn};
+use crate::{AResult, AppState};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetPeriod {
+    Daily,
+    Weekly,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetOp {
+    #[default]
+    Gte,
+    Lte,
+}
+
+/// e.g. "water >= 2000 ml daily" or "weight <= 80 kg weekly average"
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordTarget {
+    pub period: TargetPeriod,
+    pub amount: Decimal, // in the unit of the RecordProto, e.g. 2000 (ml) daily
+    #[serde(default)]
+    pub op: TargetOp,
+    #[serde(default, rename = "fn")]
+    pub function: AggregateFn, // how records of a period are combined, sum by default
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordProto {
+    pub pk: String,   // "RecordProto::Active" || "RecordProto::Inactive"
+    pub sk: String,   // "Record::" + canonical name, e.g. "Record::water"
+    pub name: String, // canonical name stored in every Record, e.g. "water"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>, // the only unit records are stored in, e.g. "ml"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<Decimal>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<Decimal>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<RecordTarget>,
+}
+
+#[derive(Deserialize)]
+pub struct RecordProtoFC {
+    pub name: String, // e.g. "Water", stored as "water"
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub min: Option<Decimal>,
+    #[serde(default)]
+    pub max: Option<Decimal>,
+    pub target: Option<RecordTarget>,
+}
+
+/// Lowercases and collapses whitespace, so that "Water " and "water" are the same record.
+pub fn normalize_record_name(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub fn record_proto_sk(name: &str) -> String {
+    format!("Record::{}", normalize_record_name(name))
+}
+
+impl RecordProto {
+    pub fn new(r_fc: RecordProtoFC, pk: impl Into<String>) -> AResult<Self> {
+        let name = normalize_record_name(&r_fc.name);
+        if name.is_empty() {
+            return Err(anyhow::Error::msg("RecordProto name must not be empty").into());
+        }
+        if let (Some(min), Some(max)) = (r_fc.min, r_fc.max) {
+            if min > max {
+                return Err(anyhow::Error::msg("RecordProto must have min <= max").into());
+            }
+        }
+        if r_fc
+            .target
+            .as_ref()
+            .is_some_and(|t| t.amount.is_sign_negative())
+        {
+            return Err(anyhow::Error::msg("RecordProto target must not be negative").into());
+        }
+
+        Ok(Self {
+            pk: pk.into(),
+            sk: record_proto_sk(&name),
+            name,
+            unit: normalize_unit(r_fc.unit),
+            min: r_fc.min,
+            max: r_fc.max,
+            target: r_fc.target,
+        })
+    }
+}
+
+impl RecordProto {
+    pub async fn set_as_active(state: &AppState, sk: impl Into<String>) -> AResult<()> {
+        let sk = sk.into();
+        let mut found_inactive =
+            match RecordProto::ddb_find(state, "RecordProto::Inactive", &sk).await {
+                Ok(res) => res,
+                Err(_) => {
+                    return Err(anyhow::Error::msg(
+                        "Inactive RecordProto with given sort key does not exist",
+                    )
+                    .into())
+                }
+            };
+
+        if RecordProto::ddb_find(state, "RecordProto::Active", &sk)
+            .await
+            .is_ok()
+        {
+            return Err(anyhow::Error::msg(
+                "Active RecordProto with given sort key already exists",
+            )
+            .into());
+        };
+
+        found_inactive.pk = String::from("RecordProto::Active");
+        RecordProto::ddb_put_item(state, found_inactive).await?;
+        RecordProto::ddb_delete(state, "RecordProto::Inactive", sk).await?;
+        Ok(())
+    }
+
+    pub async fn set_as_inactive(state: &AppState, sk: impl Into<String>) -> AResult<()> {
+        let sk = sk.into();
+        let mut found_active = match RecordProto::ddb_find(state, "RecordProto::Active", &sk).await
+        {
+            Ok(res) => res,
+            Err(_) => {
+                return Err(anyhow::Error::msg(
+                    "Active RecordProto with given sort key does not exist",
+                )
+                .into())
+            }
+        };
+
+        if RecordProto::ddb_find(state, "RecordProto::Inactive", &sk)
+            .await
+            .is_ok()
+        {
+            return Err(anyhow::Error::msg(
+                "Inactive RecordProto with given sort key already exists",
+            )
+            .into());
+        };
+
+        found_active.pk = String::from("RecordProto::Inactive");
+        RecordProto::ddb_put_item(state, found_active).await?;
+        RecordProto::ddb_delete(state, "RecordProto::Active", sk).await?;
+        Ok(())
+    }
+
+    pub async fn create(state: &AppState, record_proto_fc: RecordProtoFC) -> AResult<RecordProto> {
+        let record_proto = RecordProto::new(record_proto_fc, "RecordProto::Active")?;
+        for pk in ["RecordProto::Active", "RecordProto::Inactive"] {
+            if RecordProto::ddb_find(state, pk, &record_proto.sk)
+                .await
+                .is_ok()
+            {
+                return Err(
+                    anyhow::Error::msg("RecordProto with given name already exists").into(),
+                );
+            }
+        }
+
+        RecordProto::ddb_put_item(state, record_proto.clone()).await?;
+        Ok(record_proto)
+    }
+
+    /// Replaces the definition of an active or inactive RecordProto with the same name.
+    pub async fn update(state: &AppState, record_proto_fu: RecordProtoFC) -> AResult<RecordProto> {
+        let sk = record_proto_sk(&record_proto_fu.name);
+        let active_exists = RecordProto::ddb_find(state, "RecordProto::Active", &sk)
+            .await
+            .is_ok();
+        let inactive_exists = RecordProto::ddb_find(state, "RecordProto::Inactive", &sk)
+            .await
+            .is_ok();
+
+        let pk = match (active_exists, inactive_exists) {
+            (true, true) => {
+                return Err(anyhow::Error::msg("Corrupted data - RecordProto with given sort key exists in both active and inactive lists").into());
+            }
+            (true, false) => "RecordProto::Active",
+            (false, true) => "RecordProto::Inactive",
+            (false, false) => {
+                return Err(
+                    anyhow::Error::msg("RecordProto with given sort key does not exist").into(),
+                );
+            }
+        };
+
+        let record_proto = RecordProto::new(record_proto_fu, pk)?;
+        RecordProto::ddb_put_item(state, record_proto.clone()).await?;
+        Ok(record_proto)
+    }
+
+    /// Finds the active RecordProto for a record name, explaining why there is none.
+    pub async fn find_active_for(state: &AppState, name: &str) -> AResult<RecordProto> {
+        let sk = record_proto_sk(name);
+        if let Ok(res) = RecordProto::ddb_find(state, "RecordProto::Active", &sk).await {
+            return Ok(res);
+        }
+        if RecordProto::ddb_find(state, "RecordProto::Inactive", &sk)
+            .await
+            .is_ok()
+        {
+            return Err(anyhow::Error::msg(format!("RecordProto '{}' is inactive", name)).into());
+        }
+        Err(anyhow::Error::msg(format!("Unknown record name '{}'", name)).into())
+    }
+}
+
+// Functions for direct interaction with DynamoDB
+impl RecordProto {
+    pub async fn ddb_find(
+        state: &AppState,
+        pk: impl Into<String>,
+        sk: impl Into<String>,
+    ) -> AResult<RecordProto> {
+        let pk = pk.into();
+        let sk = sk.into();
+        if (pk != "RecordProto::Active") && (pk != "RecordProto::Inactive") {
+            return Err(
+                anyhow::Error::msg("Invalid RecordProto query partition key argument").into(),
+            );
+        }
+        if !sk.starts_with("Record::") {
+            return Err(anyhow::Error::msg("Invalid RecordProto query sort key argument").into());
+        }
+
+        let res = state
+            .dynamodb_client
+            .get_item()
+            .table_name(&state.table_name)
+            .key("pk", AttributeValue::S(pk))
+            .key("sk", AttributeValue::S(sk))
+            .send()
+            .await?;
+
+        let item = res
+            .item
+            .ok_or(anyhow::Error::msg("Error querying DynamoDB RecordProtos"))?;
+
+        Ok(from_item(item)?)
+    }
+
+    async fn ddb_put_item(state: &AppState, record_proto: RecordProto) -> AResult<()> {
+        if (record_proto.pk != "RecordProto::Active")
+            && (record_proto.pk != "RecordProto::Inactive")
+        {
+            return Err(anyhow::Error::msg("Invalid RecordProto partition key").into());
+        }
+        if !record_proto.sk.starts_with("Record::") {
+            return Err(anyhow::Error::msg("Invalid RecordProto sort key").into());
+        }
+
+        state
+            .dynamodb_client
+            .put_item()
+            .table_name(&state.table_name)
+            .set_item(Some(to_item(record_proto)?))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn ddb_list(state: &AppState, pk: &str) -> AResult<Vec<RecordProto>> {
+        let res = state
+            .dynamodb_client
+            .query()
+            .table_name(&state.table_name)
+            .key_condition_expression("pk = :pk")
+            .expression_attribute_values(":pk", AttributeValue::S(pk.to_string()))
+            .send()
+            .await?;
+
+        match res.items {
+            Some(items) => Ok(from_items(items)?),
+            None => Err(anyhow::Error::msg("Error querying DynamoDB RecordProtos").into()),
+        }
+    }
+
+    pub async fn ddb_list_active(state: &AppState) -> AResult<Vec<RecordProto>> {
+        RecordProto::ddb_list(state, "RecordProto::Active").await
+    }
+
+    pub async fn ddb_list_inactive(state: &AppState) -> AResult<Vec<RecordProto>> {
+        RecordProto::ddb_list(state, "RecordProto::Inactive").await
+    }
+
+    async fn ddb_delete(
+        state: &AppState,
+        pk: impl Into<String>,
+        sk: impl Into<String>,
+    ) -> AResult<()> {
+        let pk = pk.into();
+        if !pk.starts_with("RecordProto::") {
+            return Err(anyhow::Error::msg("Invalid RecordProto primary key").into());
+        }
+        state
+            .dynamodb_client
+            .delete_item()
+            .table_name(&state.table_name)
+            .key("pk", AttributeValue::S(pk))
+            .key("sk", AttributeValue::S(sk.into()))
+            .send()
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fc(json: &str) -> RecordProtoFC {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_new() {
+        let proto = RecordProto::new(
+            fc(
+                r#"{"name": "  Body   Weight ", "unit": "Kilograms", "min": 30, "max": 250.5,
+                   "target": {"period": "weekly", "amount": 80}}"#,
+            ),
+            "RecordProto::Active",
+        )
+        .unwrap();
+        assert_eq!(proto.sk, "Record::body weight");
+        assert_eq!(proto.name, "body weight");
+        assert_eq!(proto.unit.as_deref(), Some("kg"));
+        assert_eq!(
+            proto.max.map(|m| m.to_string()),
+            Some(String::from("250.5"))
+        );
+        assert_eq!(proto.target.map(|t| t.period), Some(TargetPeriod::Weekly));
+
+        assert!(RecordProto::new(fc(r#"{"name": " "}"#), "RecordProto::Active").is_err());
+        assert!(RecordProto::new(
+            fc(r#"{"name": "water", "min": 5, "max": 1}"#),
+            "RecordProto::Active"
+        )
+        .is_err());
+    }
+}
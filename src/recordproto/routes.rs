@@ -0,0 +1,69 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use super::{RecordProto, RecordProtoFC};
+use crate::{AResult, AppState};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", post(create))
+        .route("/", put(update))
+        .route("/:pk/:sk", get(find))
+        .route("/active", get(list_active))
+        .route("/inactive", get(list_inactive))
+        .route("/active/:sk", put(set_as_active))
+        .route("/inactive/:sk", put(set_as_inactive))
+}
+
+async fn set_as_active(
+    State(state): State<AppState>,
+    Path(sk): Path<String>,
+) -> AResult<StatusCode> {
+    RecordProto::set_as_active(&state, sk).await?;
+    Ok(StatusCode::CREATED)
+}
+
+async fn set_as_inactive(
+    State(state): State<AppState>,
+    Path(sk): Path<String>,
+) -> AResult<StatusCode> {
+    RecordProto::set_as_inactive(&state, sk).await?;
+    Ok(StatusCode::CREATED)
+}
+
+async fn list_active(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
+    let response = RecordProto::ddb_list_active(&state).await?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn list_inactive(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
+    let response = RecordProto::ddb_list_inactive(&state).await?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn find(
+    State(state): State<AppState>,
+    Path((pk, sk)): Path<(String, String)>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let response = RecordProto::ddb_find(&state, pk, sk).await?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn create(
+    State(state): State<AppState>,
+    Json(payload): Json<RecordProtoFC>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let response = RecordProto::create(&state, payload).await?;
+    Ok((StatusCode::CREATED, Json(json!(response))))
+}
+
+async fn update(
+    State(state): State<AppState>,
+    Json(payload): Json<RecordProtoFC>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let response = RecordProto::update(&state, payload).await?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
@@ -0,0 +1,10 @@
+mod model;
+mod routes;
+mod text;
+
+pub use model::DocType;
+pub use model::SearchDoc;
+pub use model::SearchParams;
+pub use model::SearchQuery;
+pub use routes::router;
+pub use text::{highlight_snippet, term_frequencies, tokenize};
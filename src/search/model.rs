@@ -0,0 +1,441 @@
+use std::collections::HashMap;
+
+use aws_sdk_dynamodb::types::{AttributeValue, WriteRequest};
+use serde::{Deserialize, Serialize};
+use serde_dynamo::{from_item, from_items, to_item};
+
+use super::{highlight_snippet, term_frequencies, tokenize};
+use crate::archive::ARCHIVE_SK;
+use crate::utils::ddb::{
+    ddb_batch_get, ddb_batch_write, ddb_key, ddb_query_partition, delete_request, put_request,
+    DdbItem,
+};
+use crate::{AResult, AppState};
+
+pub const SEARCH_DOC_PK: &str = "Search::Doc";
+pub const SEARCH_TERM_PK_PREFIX: &str = "Search::Term::";
+
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DocType {
+    Entry,
+    Archive,
+    Task,
+}
+
+impl DocType {
+    pub fn from_doc_pk(doc_pk: &str) -> AResult<DocType> {
+        if doc_pk.starts_with("Entry::") {
+            Ok(DocType::Entry)
+        } else if doc_pk == ARCHIVE_SK {
+            Ok(DocType::Archive)
+        } else if doc_pk.starts_with("Task::") {
+            Ok(DocType::Task)
+        } else {
+            Err(
+                anyhow::Error::msg(format!("Cannot index item with partition key {}", doc_pk))
+                    .into(),
+            )
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<DocType, String> {
+        match s.trim() {
+            "entry" => Ok(DocType::Entry),
+            "archive" => Ok(DocType::Archive),
+            "task" => Ok(DocType::Task),
+            other => Err(format!("Invalid search result type: {}", other)),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            DocType::Entry => "entry",
+            DocType::Archive => "archive",
+            DocType::Task => "task",
+        }
+    }
+
+    // attribute holding the indexed text of the source item
+    fn text_attribute(&self) -> &'static str {
+        match self {
+            DocType::Entry | DocType::Archive => "content",
+            DocType::Task => "description",
+        }
+    }
+}
+
+/// Bookkeeping item listing the terms an item was indexed under, so they can be removed later.
+#[derive(Serialize, Deserialize)]
+pub struct SearchDoc {
+    pub pk: String, // "Search::Doc"
+    pub sk: String, // "<doc pk>#<doc sk>", e.g. "Entry::Dream#2024-05-01"
+    pub terms: Vec<String>,
+}
+
+/// Posting item, one per (term, indexed item) pair.
+#[derive(Serialize, Deserialize)]
+pub struct SearchTerm {
+    pub pk: String, // e.g. "Search::Term::ocean"
+    pub sk: String, // "<doc pk>#<doc sk>", e.g. "Entry::Dream#2024-05-01"
+    pub doc_pk: String,
+    pub doc_sk: String,
+    pub doc_type: DocType,
+    pub date: String, // e.g. "2024-05-01"
+    pub tf: u32,      // how many times the term occurs in the indexed text
+}
+
+#[derive(Deserialize)]
+pub struct SearchParams {
+    pub q: String,
+    #[serde(rename = "type")]
+    pub doc_type: Option<String>, // comma separated, e.g. "entry,archive"
+    pub from: Option<String>, // e.g. "2024-01-01"
+    pub to: Option<String>,   // e.g. "2024-12-31"
+    pub limit: Option<usize>,
+}
+
+/// Validated SearchParams.
+pub struct SearchQuery {
+    pub q: String,
+    pub terms: Vec<String>,      // sorted and deduplicated
+    pub doc_types: Vec<DocType>, // all types when empty
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub limit: usize,
+}
+
+impl SearchQuery {
+    /// The error message is meant for the client.
+    pub fn parse(params: SearchParams) -> Result<SearchQuery, String> {
+        let mut terms = tokenize(&params.q);
+        terms.sort();
+        terms.dedup();
+        if terms.is_empty() {
+            return Err(String::from("Search query contains no searchable terms"));
+        }
+
+        let doc_types: Vec<DocType> = match &params.doc_type {
+            Some(types) => types
+                .split(',')
+                .filter(|t| !t.trim().is_empty())
+                .map(DocType::parse)
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+
+        Ok(SearchQuery {
+            q: params.q,
+            terms,
+            doc_types,
+            from: params.from,
+            to: params.to,
+            limit: params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        })
+    }
+}
+
+#[derive(Serialize)]
+pub struct SearchHit {
+    pub doc_type: DocType,
+    pub pk: String,
+    pub sk: String,
+    pub date: String,
+    pub score: f64,
+    pub matched_terms: Vec<String>,
+    pub snippet: String,
+}
+
+#[derive(Serialize)]
+pub struct SearchResult {
+    pub query: String,
+    pub total: usize, // estimate, stale postings are only noticed among the fetched hits
+    pub hits: Vec<SearchHit>,
+}
+
+fn doc_key(doc_pk: &str, doc_sk: &str) -> String {
+    format!("{}#{}", doc_pk, doc_sk)
+}
+
+impl SearchDoc {
+    /// Replaces whatever was indexed for the given item with terms of `text`.
+    pub async fn ddb_reindex(
+        state: &AppState,
+        doc_pk: impl Into<String>,
+        doc_sk: impl Into<String>,
+        text: Option<&str>,
+    ) -> AResult<()> {
+        let doc_pk = doc_pk.into();
+        let doc_sk = doc_sk.into();
+        let doc_type = DocType::from_doc_pk(&doc_pk)?;
+
+        SearchDoc::ddb_unindex(state, &doc_pk, &doc_sk).await?;
+
+        let frequencies = term_frequencies(text.unwrap_or_default());
+        if frequencies.is_empty() {
+            return Ok(());
+        }
+
+        let key = doc_key(&doc_pk, &doc_sk);
+        let date: String = doc_sk.chars().take(10).collect();
+        let mut requests: Vec<WriteRequest> = Vec::new();
+        let mut terms: Vec<String> = Vec::new();
+
+        for (term, tf) in frequencies {
+            let posting = SearchTerm {
+                pk: format!("{}{}", SEARCH_TERM_PK_PREFIX, term),
+                sk: key.clone(),
+                doc_pk: doc_pk.clone(),
+                doc_sk: doc_sk.clone(),
+                doc_type,
+                date: date.clone(),
+                tf,
+            };
+            requests.push(put_request(to_item(posting)?)?);
+            terms.push(term);
+        }
+
+        terms.sort();
+        let doc = SearchDoc {
+            pk: String::from(SEARCH_DOC_PK),
+            sk: key,
+            terms,
+        };
+        requests.push(put_request(to_item(doc)?)?);
+
+        ddb_batch_write(state, requests).await
+    }
+
+    /// Removes all postings of the given item. Does nothing if the item was never indexed.
+    pub async fn ddb_unindex(
+        state: &AppState,
+        doc_pk: impl Into<String>,
+        doc_sk: impl Into<String>,
+    ) -> AResult<()> {
+        let key = doc_key(&doc_pk.into(), &doc_sk.into());
+        let res = state
+            .dynamodb_client
+            .get_item()
+            .table_name(&state.table_name)
+            .key("pk", AttributeValue::S(String::from(SEARCH_DOC_PK)))
+            .key("sk", AttributeValue::S(key.clone()))
+            .send()
+            .await?;
+
+        let doc: SearchDoc = match res.item {
+            Some(item) => from_item(item)?,
+            None => return Ok(()),
+        };
+
+        let mut requests: Vec<WriteRequest> = Vec::new();
+        for term in doc.terms {
+            requests.push(delete_request(ddb_key(
+                format!("{}{}", SEARCH_TERM_PK_PREFIX, term),
+                &key,
+            ))?);
+        }
+        requests.push(delete_request(ddb_key(SEARCH_DOC_PK, &key))?);
+
+        ddb_batch_write(state, requests).await
+    }
+
+    /// Rebuilds postings for every entry, archive entry and task description in the table.
+    /// Returns the number of reindexed items.
+    pub async fn ddb_rebuild(state: &AppState) -> AResult<usize> {
+        let mut partitions: Vec<String> = vec![String::from(ARCHIVE_SK)];
+        for proto_pk in [
+            "EntryProto::Active",
+            "EntryProto::Inactive",
+            "TaskProto::Active",
+            "TaskProto::Inactive",
+        ] {
+            for proto in ddb_query_partition(state, proto_pk).await? {
+                if let Some(AttributeValue::S(sk)) = proto.get("sk") {
+                    partitions.push(sk.clone());
+                }
+            }
+        }
+
+        let mut count = 0;
+        for partition in partitions {
+            let doc_type = DocType::from_doc_pk(&partition)?;
+            for item in ddb_query_partition(state, &partition).await? {
+                let (Some(AttributeValue::S(pk)), Some(AttributeValue::S(sk))) =
+                    (item.get("pk"), item.get("sk"))
+                else {
+                    continue;
+                };
+                SearchDoc::ddb_reindex(state, pk, sk, item_text(&item, doc_type)).await?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    pub async fn ddb_search(state: &AppState, query: SearchQuery) -> AResult<SearchResult> {
+        let mut hits: HashMap<String, SearchHit> = HashMap::new();
+        for term in &query.terms {
+            let postings = SearchTerm::ddb_query(state, term, &query).await?;
+            let idf = 1.0 / (1.0 + (postings.len() as f64).ln());
+
+            for posting in postings {
+                let hit = hits.entry(posting.sk).or_insert_with(|| SearchHit {
+                    doc_type: posting.doc_type,
+                    pk: posting.doc_pk,
+                    sk: posting.doc_sk,
+                    date: posting.date,
+                    score: 0.0,
+                    matched_terms: Vec::new(),
+                    snippet: String::new(),
+                });
+                hit.score += (1.0 + (posting.tf as f64).ln()) * idf;
+                hit.matched_terms.push(term.clone());
+            }
+        }
+
+        let mut hits: Vec<SearchHit> = hits.into_values().collect();
+        // items matching more of the query always rank first, newer items win ties
+        hits.sort_by(|a, b| {
+            b.matched_terms
+                .len()
+                .cmp(&a.matched_terms.len())
+                .then(b.score.total_cmp(&a.score))
+                .then(b.date.cmp(&a.date))
+        });
+
+        // source items are only read for as many hits as are returned; postings of items
+        // deleted outside of the API are dropped on the way and fetched for again
+        let mut total = hits.len();
+        let mut found: Vec<SearchHit> = Vec::new();
+        let mut rest = hits.into_iter();
+        while found.len() < query.limit {
+            let page: Vec<SearchHit> = rest.by_ref().take(query.limit - found.len()).collect();
+            if page.is_empty() {
+                break;
+            }
+            let texts = ddb_get_texts(state, &page).await?;
+            for mut hit in page {
+                match texts.get(&doc_key(&hit.pk, &hit.sk)) {
+                    Some(text) => {
+                        hit.snippet = highlight_snippet(text, &hit.matched_terms);
+                        found.push(hit);
+                    }
+                    None => total -= 1,
+                }
+            }
+        }
+
+        Ok(SearchResult {
+            query: query.q,
+            total,
+            hits: found,
+        })
+    }
+}
+
+// indexed texts of the source items of the hits, by doc key
+async fn ddb_get_texts(state: &AppState, hits: &[SearchHit]) -> AResult<HashMap<String, String>> {
+    let keys: Vec<DdbItem> = hits.iter().map(|h| ddb_key(&h.pk, &h.sk)).collect();
+    Ok(ddb_batch_get(state, keys)
+        .await?
+        .into_iter()
+        .filter_map(|item| {
+            let Some(AttributeValue::S(pk)) = item.get("pk") else {
+                return None;
+            };
+            let Some(AttributeValue::S(sk)) = item.get("sk") else {
+                return None;
+            };
+            let doc_type = DocType::from_doc_pk(pk).ok()?;
+            let text = item_text(&item, doc_type)?;
+            Some((doc_key(pk, sk), text.to_string()))
+        })
+        .collect())
+}
+
+impl SearchTerm {
+    async fn ddb_query(
+        state: &AppState,
+        term: &str,
+        search: &SearchQuery,
+    ) -> AResult<Vec<SearchTerm>> {
+        let mut filters: Vec<String> = Vec::new();
+        let mut query = state
+            .dynamodb_client
+            .query()
+            .table_name(&state.table_name)
+            .key_condition_expression("pk = :pk")
+            .expression_attribute_values(
+                ":pk",
+                AttributeValue::S(format!("{}{}", SEARCH_TERM_PK_PREFIX, term)),
+            );
+
+        if let Some(from) = &search.from {
+            filters.push(String::from("#date >= :from"));
+            query = query.expression_attribute_values(":from", AttributeValue::S(from.clone()));
+        }
+        if let Some(to) = &search.to {
+            filters.push(String::from("#date <= :to"));
+            query = query.expression_attribute_values(":to", AttributeValue::S(to.clone()));
+        }
+        if search.from.is_some() || search.to.is_some() {
+            query = query.expression_attribute_names("#date", "date");
+        }
+
+        if !search.doc_types.is_empty() {
+            let mut placeholders: Vec<String> = Vec::new();
+            for (i, doc_type) in search.doc_types.iter().enumerate() {
+                let placeholder = format!(":type{}", i);
+                query = query.expression_attribute_values(
+                    &placeholder,
+                    AttributeValue::S(doc_type.as_str().to_string()),
+                );
+                placeholders.push(placeholder);
+            }
+            filters.push(format!("doc_type IN ({})", placeholders.join(", ")));
+        }
+
+        if !filters.is_empty() {
+            query = query.filter_expression(filters.join(" AND "));
+        }
+
+        let items: Vec<DdbItem> = query.into_paginator().items().send().try_collect().await?;
+        Ok(from_items(items)?)
+    }
+}
+
+fn item_text(item: &DdbItem, doc_type: DocType) -> Option<&str> {
+    match item.get(doc_type.text_attribute()) {
+        Some(AttributeValue::S(text)) => Some(text),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(q: &str, doc_type: Option<&str>) -> SearchParams {
+        SearchParams {
+            q: q.to_string(),
+            doc_type: doc_type.map(String::from),
+            from: None,
+            to: None,
+            limit: Some(1000),
+        }
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = SearchQuery::parse(params("Ocean dream ocean", Some("entry, task,"))).unwrap();
+        assert_eq!(query.terms, vec!["dream", "ocean"]);
+        assert_eq!(query.doc_types, vec![DocType::Entry, DocType::Task]);
+        assert_eq!(query.limit, MAX_LIMIT);
+
+        assert!(SearchQuery::parse(params("", None)).is_err());
+        assert!(SearchQuery::parse(params("ocean", Some("entry,mood"))).is_err());
+    }
+}
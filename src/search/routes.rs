@@ -0,0 +1,33 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde_json::{json, Value};
+
+use super::{SearchDoc, SearchParams, SearchQuery};
+use crate::{AResult, AppState};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(search))
+        .route("/reindex", post(reindex))
+}
+
+async fn search(
+    State(state): State<AppState>,
+    Query(params): Query<SearchParams>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let query = match SearchQuery::parse(params) {
+        Ok(query) => query,
+        Err(message) => {
+            return Ok((StatusCode::BAD_REQUEST, Json(json!({ "message": message }))));
+        }
+    };
+    let response = SearchDoc::ddb_search(&state, query).await?;
+    Ok((StatusCode::OK, Json(json!(response))))
+}
+
+async fn reindex(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
+    let indexed = SearchDoc::ddb_rebuild(&state).await?;
+    Ok((StatusCode::OK, Json(json!({ "indexed": indexed }))))
+}
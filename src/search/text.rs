@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+
+const MIN_TERM_LEN: usize = 2;
+const MAX_TERM_LEN: usize = 64;
+const SNIPPET_RADIUS: usize = 80; // bytes of context kept on each side of the first match
+
+const STOP_WORDS: [&str; 32] = [
+    "an", "and", "are", "as", "at", "be", "but", "by", "for", "from", "if", "in", "into", "is",
+    "it", "its", "no", "not", "of", "on", "or", "so", "such", "that", "the", "their", "then",
+    "there", "these", "this", "to", "was",
+];
+
+struct TokenSpan {
+    start: usize,
+    end: usize,
+    term: String,
+}
+
+fn token_spans(text: &str) -> Vec<TokenSpan> {
+    let mut spans: Vec<TokenSpan> = Vec::new();
+    let mut start: Option<usize> = None;
+
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                let term = text[s..i].to_lowercase();
+                let len = term.chars().count();
+                if (MIN_TERM_LEN..=MAX_TERM_LEN).contains(&len)
+                    && !STOP_WORDS.contains(&term.as_str())
+                {
+                    spans.push(TokenSpan {
+                        start: s,
+                        end: i,
+                        term,
+                    });
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+/// Splits text into lowercase search terms, skipping stop words and very short or long tokens.
+pub fn tokenize(text: &str) -> Vec<String> {
+    token_spans(text).into_iter().map(|s| s.term).collect()
+}
+
+pub fn term_frequencies(text: &str) -> HashMap<String, u32> {
+    let mut frequencies: HashMap<String, u32> = HashMap::new();
+    for term in tokenize(text) {
+        *frequencies.entry(term).or_insert(0) += 1;
+    }
+    frequencies
+}
+
+/// Returns a fragment of `text` around the first matching term, with matches wrapped in `<mark>`.
+pub fn highlight_snippet(text: &str, terms: &[String]) -> String {
+    let spans = token_spans(text);
+    let matches: Vec<&TokenSpan> = spans.iter().filter(|s| terms.contains(&s.term)).collect();
+    let anchor = matches.first().map(|s| s.start).unwrap_or(0);
+
+    let mut start = anchor.saturating_sub(SNIPPET_RADIUS);
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (anchor + SNIPPET_RADIUS).min(text.len());
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+
+    let mut snippet = String::new();
+    if start > 0 {
+        snippet.push('…');
+    }
+    let mut cursor = start;
+    for span in matches.iter().filter(|s| s.start >= start && s.end <= end) {
+        snippet.push_str(&text[cursor..span.start]);
+        snippet.push_str("<mark>");
+        snippet.push_str(&text[span.start..span.end]);
+        snippet.push_str("</mark>");
+        cursor = span.end;
+    }
+    snippet.push_str(&text[cursor..end]);
+    if end < text.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("The dream about the OCEAN, again: ocean-waves & a boat!"),
+            vec!["dream", "about", "ocean", "again", "ocean", "waves", "boat"]
+        );
+        assert_eq!(
+            tokenize("Śniło mi się morze"),
+            vec!["śniło", "mi", "się", "morze"]
+        );
+        assert!(tokenize(" - a ").is_empty());
+    }
+
+    #[test]
+    fn test_term_frequencies() {
+        let tf = term_frequencies("Ocean, ocean and more OCEAN");
+        assert_eq!(tf.get("ocean"), Some(&3));
+        assert_eq!(tf.get("more"), Some(&1));
+        assert_eq!(tf.get("and"), None);
+    }
+
+    #[test]
+    fn test_highlight_snippet() {
+        let terms = vec![String::from("ocean")];
+        assert_eq!(
+            highlight_snippet("I dreamt about the Ocean.", &terms),
+            "I dreamt about the <mark>Ocean</mark>."
+        );
+
+        let long = format!("{} ocean {}", "x".repeat(200), "y".repeat(200));
+        let snippet = highlight_snippet(&long, &terms);
+        assert!(snippet.starts_with('…'));
+        assert!(snippet.ends_with('…'));
+        assert!(snippet.contains("<mark>ocean</mark>"));
+
+        let multibyte = format!("{}ocean", "ż ".repeat(100));
+        assert!(highlight_snippet(&multibyte, &terms).ends_with("<mark>ocean</mark>"));
+    }
+}
@@ -3,5 +3,5 @@ mod routes;
 
 pub use model::Task;
 pub use model::TaskFC;
-pub use routes::find_last_week_tasks;
+pub use routes::find_tasks_in_window;
 pub use routes::router;
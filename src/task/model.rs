@@ -1,11 +1,13 @@
 use aws_sdk_dynamodb::types::AttributeValue;
 use chrono::{Duration, Utc};
-use chrono_tz::Europe;
 use serde::{Deserialize, Serialize};
 use serde_dynamo::{from_items, to_item};
 use std::convert::Into;
 
-use crate::utils::time::{get_date_x_days_ago, get_today_datetime};
+use crate::search::SearchDoc;
+use crate::utils::ddb::{ddb_put_new, DdbItem};
+use crate::utils::id::new_id;
+use crate::utils::time::{get_date_x_days_ago, TIMEZONE};
 use crate::AppState;
 use crate::{taskproto::TaskProto, AResult};
 
@@ -41,10 +43,10 @@ pub struct TaskFC {
 
 // DynamoDB handlers
 impl Task {
-    pub async fn ddb_create(state: &AppState, task_fc: TaskFC) -> AResult<()> {
+    pub async fn ddb_create(state: &AppState, task_fc: TaskFC) -> AResult<Task> {
         let mut task_to_create: Task = Task::default();
 
-        let task_proto = match TaskProto::ddb_find(&state, "TaskProto::Active", &task_fc.pk).await {
+        let task_proto = match TaskProto::ddb_find(state, "TaskProto::Active", &task_fc.pk).await {
             Ok(res) => res,
             Err(_) => {
                 return Err(anyhow::Error::msg(format!(
@@ -56,7 +58,7 @@ impl Task {
         };
 
         task_to_create.pk = task_proto.sk;
-        task_to_create.sk = get_today_datetime();
+        task_to_create.sk = new_id();
         task_to_create.readable_name = task_proto.readable_name;
 
         if task_proto.has_description {
@@ -71,11 +73,11 @@ impl Task {
             }
         }
 
-        if task_proto.has_streak == true {
+        if task_proto.has_streak {
             let last_week_tasks =
-                Task::last_7_days_of_given_task(&state, &task_to_create.pk).await?;
+                Task::last_7_days_of_given_task(state, &task_to_create.pk).await?;
 
-            if task_proto.has_reps == true {
+            if task_proto.has_reps {
                 let current_rep_data = Task::compute_reps_streak(
                     task_proto.daily_reps_minimum.unwrap(),
                     task_proto.weekly_streak_tolerance.unwrap(),
@@ -93,21 +95,23 @@ impl Task {
 
                 task_to_create.streak = Some(Task::compute_non_reps_streak(
                     task_proto.weekly_streak_tolerance.unwrap(),
-                    &Task::last_7_days_of_given_task(&state, &task_to_create.pk).await?,
+                    &Task::last_7_days_of_given_task(state, &task_to_create.pk).await?,
                 )?);
             }
         }
 
-        let item = to_item(task_to_create)?;
+        ddb_put_new(state, to_item(&task_to_create)?).await?;
 
-        let req = state
-            .dynamodb_client
-            .put_item()
-            .table_name(&state.table_name)
-            .set_item(Some(item));
-
-        req.send().await?;
-        Ok(())
+        if task_to_create.description.is_some() {
+            SearchDoc::ddb_reindex(
+                state,
+                &task_to_create.pk,
+                &task_to_create.sk,
+                task_to_create.description.as_deref(),
+            )
+            .await?;
+        }
+        Ok(task_to_create)
     }
 
     pub async fn ddb_delete(
@@ -116,6 +120,7 @@ impl Task {
         sk: impl Into<String>,
     ) -> AResult<()> {
         let pk = pk.into();
+        let sk = sk.into();
         if !pk.starts_with("Task::") {
             return Err(anyhow::Error::msg("Invalid Task primary key").into());
         }
@@ -123,13 +128,38 @@ impl Task {
             .dynamodb_client
             .delete_item()
             .table_name(&state.table_name)
-            .key("pk", AttributeValue::S(pk))
-            .key("sk", AttributeValue::S(sk.into()));
+            .key("pk", AttributeValue::S(pk.clone()))
+            .key("sk", AttributeValue::S(sk.clone()));
 
         req.send().await?;
+
+        SearchDoc::ddb_unindex(state, pk, sk).await?;
         Ok(())
     }
 
+    pub async fn ddb_query_from_to(
+        state: &AppState,
+        pk: impl Into<String>,
+        from: impl Into<String>,
+        to: impl Into<String>,
+    ) -> AResult<Vec<Task>> {
+        let items: Vec<DdbItem> = state
+            .dynamodb_client
+            .query()
+            .table_name(&state.table_name)
+            .key_condition_expression("pk = :pk AND sk BETWEEN :from AND :to")
+            .expression_attribute_values(":pk", AttributeValue::S(pk.into()))
+            .expression_attribute_values(":from", AttributeValue::S(from.into()))
+            .expression_attribute_values(":to", AttributeValue::S(to.into()))
+            .into_paginator()
+            .items()
+            .send()
+            .try_collect()
+            .await?;
+
+        Ok(from_items(items)?)
+    }
+
     pub async fn ddb_query(
         state: &AppState,
         pk: impl Into<String>,
@@ -147,11 +177,9 @@ impl Task {
         match res.items {
             Some(items) => {
                 let tasks: Vec<Task> = from_items(items)?;
-                return Ok(tasks);
-            }
-            None => {
-                return Err(anyhow::Error::msg("Error querying DynamoDB tasks. {:?}").into());
+                Ok(tasks)
             }
+            None => Err(anyhow::Error::msg("Error querying DynamoDB tasks. {:?}").into()),
         }
     }
 }
@@ -170,7 +198,7 @@ struct CurrentRepData {
 impl Task {
     fn get_streaks_with_reps_week_summary(
         daily_rep_minimum: u8,
-        last_week_tasks: &Vec<Task>,
+        last_week_tasks: &[Task],
     ) -> Vec<RepTaskDaySummary> {
         let mut summary: Vec<RepTaskDaySummary> = Vec::new();
 
@@ -204,7 +232,7 @@ impl Task {
     fn compute_reps_streak(
         daily_rep_minimum: u8,
         weekly_streak_tolerance: u8,
-        last_week_tasks: &Vec<Task>,
+        last_week_tasks: &[Task],
     ) -> AResult<CurrentRepData> {
         let mut last_found_streak: u32 = 0; // streak starts at 0 if there are no tasks for the last 7 days & today's tasks are less than daily_rep_minimum
         let mut today_streak_point: u32 = 0;
@@ -250,7 +278,7 @@ impl Task {
 
     fn compute_non_reps_streak(
         weekly_streak_tolerance: u8,
-        last_week_tasks: &Vec<Task>,
+        last_week_tasks: &[Task],
     ) -> AResult<u32> {
         let mut streak: u32 = 1; // If there are no tasks for the last 7 days, the streak starts at 1
 
@@ -279,7 +307,7 @@ impl Task {
         state: &AppState,
         pk: impl Into<String>,
     ) -> AResult<Vec<Task>> {
-        let week_ago = (Utc::now().with_timezone(&Europe::Warsaw) + Duration::days(-7))
+        let week_ago = (Utc::now().with_timezone(&TIMEZONE) + Duration::days(-7))
             .format("%Y-%m-%d")
             .to_string();
 
@@ -289,6 +317,7 @@ impl Task {
 }
 
 #[cfg(test)]
+#[allow(clippy::field_reassign_with_default)]
 mod tests {
     use super::*;
 
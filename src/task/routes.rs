@@ -7,7 +7,7 @@ use serde_json::{json, Value};
 
 use super::{Task, TaskFC};
 use crate::taskproto::TaskProto;
-use crate::utils::time::get_date_x_days_ago;
+use crate::utils::time::{get_today_date, DateWindow};
 use crate::{AResult, AppState};
 
 pub fn router() -> Router<AppState> {
@@ -27,18 +27,23 @@ pub struct ProtoWithTasks {
 async fn find_last_week_handler(
     State(state): State<AppState>,
 ) -> AResult<(StatusCode, Json<Value>)> {
-    let result_tasks = find_last_week_tasks(&state).await?;
-    return Ok((StatusCode::OK, Json(json!(result_tasks))));
+    let window = DateWindow::last_days(7, get_today_date());
+    let result_tasks = find_tasks_in_window(&state, &window).await?;
+    Ok((StatusCode::OK, Json(json!(result_tasks))))
 }
 
-pub async fn find_last_week_tasks(state: &AppState) -> AResult<Vec<ProtoWithTasks>> {
-    let active_task_list_entries = TaskProto::ddb_list_active(&state).await?;
+pub async fn find_tasks_in_window(
+    state: &AppState,
+    window: &DateWindow,
+) -> AResult<Vec<ProtoWithTasks>> {
+    let active_task_list_entries = TaskProto::ddb_list_active(state).await?;
 
-    let week_ago = get_date_x_days_ago(7);
     let mut result_tasks: Vec<ProtoWithTasks> = Vec::new();
 
     for task_list_entry in active_task_list_entries {
-        let t: Vec<Task> = Task::ddb_query(&state, &task_list_entry.sk, &week_ago).await?;
+        let t: Vec<Task> =
+            Task::ddb_query_from_to(state, &task_list_entry.sk, &window.from, window.sk_to())
+                .await?;
         result_tasks.push(ProtoWithTasks {
             proto: task_list_entry,
             tasks: t,
@@ -52,12 +57,18 @@ async fn query(
     Path((pk, sk)): Path<(String, String)>,
 ) -> AResult<(StatusCode, Json<Value>)> {
     let response = Task::ddb_query(&state, pk, sk).await?;
-    return Ok((StatusCode::OK, Json(json!(response))));
+    Ok((StatusCode::OK, Json(json!(response))))
 }
 
-async fn create(State(state): State<AppState>, Json(payload): Json<TaskFC>) -> AResult<StatusCode> {
-    Task::ddb_create(&state, payload).await?;
-    Ok(StatusCode::CREATED)
+async fn create(
+    State(state): State<AppState>,
+    Json(payload): Json<TaskFC>,
+) -> AResult<(StatusCode, Json<Value>)> {
+    let task = Task::ddb_create(&state, payload).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "pk": task.pk, "sk": task.sk })),
+    ))
 }
 
 async fn delete_task(
@@ -56,7 +56,7 @@ impl TaskProto {
     pub async fn set_as_active(state: &AppState, sk: impl Into<String>) -> AResult<()> {
         let sk = sk.into();
         let mut found_inactive_task =
-            match TaskProto::ddb_find(&state, "TaskProto::Inactive", &sk).await {
+            match TaskProto::ddb_find(state, "TaskProto::Inactive", &sk).await {
                 Ok(res) => res,
                 Err(_) => {
                     return Err(anyhow::Error::msg(
@@ -66,7 +66,7 @@ impl TaskProto {
                 }
             };
 
-        if TaskProto::ddb_find(&state, "TaskProto::Active", &sk)
+        if TaskProto::ddb_find(state, "TaskProto::Active", &sk)
             .await
             .is_ok()
         {
@@ -83,7 +83,7 @@ impl TaskProto {
 
     pub async fn set_as_inactive(state: &AppState, sk: impl Into<String>) -> AResult<()> {
         let sk = sk.into();
-        let mut found_task = match TaskProto::ddb_find(&state, "TaskProto::Active", &sk).await {
+        let mut found_task = match TaskProto::ddb_find(state, "TaskProto::Active", &sk).await {
             Ok(res) => res,
             Err(_) => {
                 return Err(anyhow::Error::msg(
@@ -93,7 +93,7 @@ impl TaskProto {
             }
         };
 
-        if TaskProto::ddb_find(&state, "TaskProto::Inactive", &sk)
+        if TaskProto::ddb_find(state, "TaskProto::Inactive", &sk)
             .await
             .is_ok()
         {
@@ -104,8 +104,8 @@ impl TaskProto {
         };
 
         found_task.pk = String::from("TaskProto::Inactive");
-        TaskProto::ddb_put_item(&state, found_task).await?;
-        TaskProto::ddb_delete(&state, "TaskProto::Active", sk).await?;
+        TaskProto::ddb_put_item(state, found_task).await?;
+        TaskProto::ddb_delete(state, "TaskProto::Active", sk).await?;
         Ok(())
     }
 
@@ -157,7 +157,7 @@ impl TaskProto {
             TaskProto::new(task_list_entry_fc, "TaskProto::Active"),
         )
         .await?;
-        return Ok(());
+        Ok(())
     }
 
     pub async fn update(state: &AppState, task_list_entry_fu: TaskProtoFC) -> AResult<()> {
@@ -185,7 +185,7 @@ impl TaskProto {
 
         let task_list_entry = TaskProto::new(task_list_entry_fu, task_list_entry_state);
         TaskProto::ddb_put_item(state, task_list_entry).await?;
-        return Ok(());
+        Ok(())
     }
 }
 
@@ -260,12 +260,10 @@ impl TaskProto {
         match res.items {
             Some(items) => {
                 let mut tasks: Vec<TaskProto> = from_items(items)?;
-                tasks.sort_by(|a, b| b.priority.cmp(&a.priority));
-                return Ok(tasks);
-            }
-            None => {
-                return Err(anyhow::Error::msg("Error querying DynamoDB tasks. {:?}").into());
+                tasks.sort_by_key(|t| std::cmp::Reverse(t.priority));
+                Ok(tasks)
             }
+            None => Err(anyhow::Error::msg("Error querying DynamoDB tasks. {:?}").into()),
         }
     }
 
@@ -284,12 +282,10 @@ impl TaskProto {
         match res.items {
             Some(items) => {
                 let mut tasks: Vec<TaskProto> = from_items(items)?;
-                tasks.sort_by(|a, b| b.priority.cmp(&a.priority));
-                return Ok(tasks);
-            }
-            None => {
-                return Err(anyhow::Error::msg("Error querying DynamoDB tasks. {:?}").into());
+                tasks.sort_by_key(|t| std::cmp::Reverse(t.priority));
+                Ok(tasks)
             }
+            None => Err(anyhow::Error::msg("Error querying DynamoDB tasks. {:?}").into()),
         }
     }
 
@@ -310,8 +306,8 @@ impl TaskProto {
             .dynamodb_client
             .delete_item()
             .table_name(&state.table_name)
-            .key("pk", AttributeValue::S(pk.into()))
-            .key("sk", AttributeValue::S(sk.into()));
+            .key("pk", AttributeValue::S(pk))
+            .key("sk", AttributeValue::S(sk));
 
         req.send().await?;
         Ok(())
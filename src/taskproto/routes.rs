@@ -23,7 +23,7 @@ async fn set_as_active(
     Path(sk): Path<String>,
 ) -> AResult<StatusCode> {
     TaskProto::set_as_active(&state, sk).await?;
-    return Ok(StatusCode::CREATED);
+    Ok(StatusCode::CREATED)
 }
 
 async fn set_as_inactive(
@@ -31,7 +31,7 @@ async fn set_as_inactive(
     Path(sk): Path<String>,
 ) -> AResult<StatusCode> {
     TaskProto::set_as_inactive(&state, sk).await?;
-    return Ok(StatusCode::CREATED);
+    Ok(StatusCode::CREATED)
 }
 
 async fn find(
@@ -39,7 +39,7 @@ async fn find(
     Path((pk, sk)): Path<(String, String)>,
 ) -> AResult<(StatusCode, Json<Value>)> {
     let response = TaskProto::ddb_find(&state, pk, sk).await?;
-    return Ok((StatusCode::OK, Json(json!(response))));
+    Ok((StatusCode::OK, Json(json!(response))))
 }
 
 async fn create(
@@ -47,7 +47,7 @@ async fn create(
     Json(payload): Json<TaskProtoFC>,
 ) -> AResult<StatusCode> {
     TaskProto::create(&state, payload).await?;
-    return Ok(StatusCode::CREATED);
+    Ok(StatusCode::CREATED)
 }
 
 async fn update(
@@ -56,14 +56,14 @@ async fn update(
 ) -> AResult<StatusCode> {
     TaskProto::update(&state, payload).await?;
 
-    return Ok(StatusCode::CREATED);
+    Ok(StatusCode::CREATED)
 }
 
 async fn list_active(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
     let response = TaskProto::ddb_list_active(&state).await?;
-    return Ok((StatusCode::OK, Json(json!(response))));
+    Ok((StatusCode::OK, Json(json!(response))))
 }
 async fn list_inactive(State(state): State<AppState>) -> AResult<(StatusCode, Json<Value>)> {
     let response = TaskProto::ddb_list_inactive(&state).await?;
-    return Ok((StatusCode::OK, Json(json!(response))));
+    Ok((StatusCode::OK, Json(json!(response))))
 }
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use aws_sdk_dynamodb::operation::transact_write_items::TransactWriteItemsError;
+use aws_sdk_dynamodb::types::{
+    AttributeValue, DeleteRequest, KeysAndAttributes, Put, PutRequest, TransactWriteItem,
+    WriteRequest,
+};
+
+use crate::{AResult, AppState};
+
+pub type DdbItem = HashMap<String, AttributeValue>;
+
+// DynamoDB limits for a single BatchWriteItem / BatchGetItem call
+const BATCH_WRITE_LIMIT: usize = 25;
+const BATCH_GET_LIMIT: usize = 100;
+const BATCH_MAX_RETRIES: u32 = 5;
+
+/// Actions allowed in a single TransactWriteItems call.
+pub const TRANSACT_WRITE_LIMIT: usize = 100;
+
+/// Sends write requests in chunks of 25, retrying unprocessed items with backoff.
+pub async fn ddb_batch_write(state: &AppState, requests: Vec<WriteRequest>) -> AResult<()> {
+    for chunk in requests.chunks(BATCH_WRITE_LIMIT) {
+        let mut pending = HashMap::from([(state.table_name.clone(), chunk.to_vec())]);
+        let mut attempt = 0;
+
+        loop {
+            let res = state
+                .dynamodb_client
+                .batch_write_item()
+                .set_request_items(Some(pending))
+                .send()
+                .await?;
+
+            pending = res.unprocessed_items.unwrap_or_default();
+            pending.retain(|_, reqs| !reqs.is_empty());
+            if pending.is_empty() {
+                break;
+            }
+
+            attempt += 1;
+            if attempt > BATCH_MAX_RETRIES {
+                return Err(anyhow::Error::msg(
+                    "DynamoDB batch write did not process all items after retries",
+                )
+                .into());
+            }
+            tokio::time::sleep(Duration::from_millis(50 * 2u64.pow(attempt))).await;
+        }
+    }
+    Ok(())
+}
+
+/// Fetches items by key in chunks of 100, retrying unprocessed keys with backoff.
+/// Order of returned items is not guaranteed and missing items are skipped.
+pub async fn ddb_batch_get(state: &AppState, keys: Vec<DdbItem>) -> AResult<Vec<DdbItem>> {
+    let mut items: Vec<DdbItem> = Vec::new();
+
+    for chunk in keys.chunks(BATCH_GET_LIMIT) {
+        let mut pending = HashMap::from([(
+            state.table_name.clone(),
+            KeysAndAttributes::builder()
+                .set_keys(Some(chunk.to_vec()))
+                .build()?,
+        )]);
+        let mut attempt = 0;
+
+        loop {
+            let mut res = state
+                .dynamodb_client
+                .batch_get_item()
+                .set_request_items(Some(pending))
+                .send()
+                .await?;
+
+            if let Some(found) = res
+                .responses
+                .as_mut()
+                .and_then(|r| r.remove(&state.table_name))
+            {
+                items.extend(found);
+            }
+
+            pending = res.unprocessed_keys.unwrap_or_default();
+            pending.retain(|_, k| !k.keys.is_empty());
+            if pending.is_empty() {
+                break;
+            }
+
+            attempt += 1;
+            if attempt > BATCH_MAX_RETRIES {
+                return Err(anyhow::Error::msg(
+                    "DynamoDB batch get did not return all items after retries",
+                )
+                .into());
+            }
+            tokio::time::sleep(Duration::from_millis(50 * 2u64.pow(attempt))).await;
+        }
+    }
+    Ok(items)
+}
+
+/// Reads every item of a partition, following `LastEvaluatedKey` pagination.
+pub async fn ddb_query_partition(state: &AppState, pk: impl Into<String>) -> AResult<Vec<DdbItem>> {
+    let items = state
+        .dynamodb_client
+        .query()
+        .table_name(&state.table_name)
+        .key_condition_expression("pk = :pk")
+        .expression_attribute_values(":pk", AttributeValue::S(pk.into()))
+        .into_paginator()
+        .items()
+        .send()
+        .try_collect()
+        .await?;
+    Ok(items)
+}
+
+pub fn ddb_key(pk: impl Into<String>, sk: impl Into<String>) -> DdbItem {
+    HashMap::from([
+        ("pk".to_string(), AttributeValue::S(pk.into())),
+        ("sk".to_string(), AttributeValue::S(sk.into())),
+    ])
+}
+
+/// Puts an item that must not exist yet, so that a key collision fails instead of
+/// silently overwriting the existing item.
+pub async fn ddb_put_new(state: &AppState, item: DdbItem) -> AResult<()> {
+    let res = state
+        .dynamodb_client
+        .put_item()
+        .table_name(&state.table_name)
+        .set_item(Some(item))
+        .condition_expression("attribute_not_exists(pk)")
+        .send()
+        .await;
+    match res {
+        Err(err)
+            if err
+                .as_service_error()
+                .is_some_and(|e| e.is_conditional_check_failed_exception()) =>
+        {
+            Err(anyhow::Error::msg("Item with the same key already exists").into())
+        }
+        Err(err) => Err(err.into()),
+        Ok(_) => Ok(()),
+    }
+}
+
+/// Writes new items in transactions of up to 100 puts, each only if its key is free.
+/// Returns the positions of items whose key is already taken; those aren't written.
+pub async fn ddb_put_new_all(state: &AppState, items: Vec<DdbItem>) -> AResult<Vec<usize>> {
+    let mut actions = Vec::new();
+    for item in items {
+        let put = Put::builder()
+            .table_name(&state.table_name)
+            .set_item(Some(item))
+            .condition_expression("attribute_not_exists(pk)")
+            .build()?;
+        actions.push(TransactWriteItem::builder().put(put).build());
+    }
+    ddb_transact_each(state, actions).await
+}
+
+/// Runs conditional actions in transactions of up to 100. Returns the positions of actions
+/// whose condition failed; those aren't applied, all others are.
+pub async fn ddb_transact_each(
+    state: &AppState,
+    actions: Vec<TransactWriteItem>,
+) -> AResult<Vec<usize>> {
+    let mut failed_positions = Vec::new();
+    let mut pending: Vec<(usize, TransactWriteItem)> = actions.into_iter().enumerate().collect();
+    while !pending.is_empty() {
+        let rest = pending.split_off(pending.len().min(TRANSACT_WRITE_LIMIT));
+        let mut chunk = std::mem::replace(&mut pending, rest);
+
+        while !chunk.is_empty() {
+            let res = state
+                .dynamodb_client
+                .transact_write_items()
+                .set_transact_items(Some(chunk.iter().map(|(_, a)| a.clone()).collect()))
+                .send()
+                .await;
+            let err = match res {
+                Ok(_) => break,
+                Err(err) => err,
+            };
+
+            // the whole transaction is cancelled, so it's retried without the failed actions
+            let failed: Vec<bool> = match err.as_service_error() {
+                Some(TransactWriteItemsError::TransactionCanceledException(e)) => e
+                    .cancellation_reasons()
+                    .iter()
+                    .map(|r| r.code() == Some("ConditionalCheckFailed"))
+                    .collect(),
+                _ => return Err(err.into()),
+            };
+            if !failed.contains(&true) {
+                return Err(err.into());
+            }
+            let mut failed = failed.into_iter();
+            chunk.retain(|(position, _)| match failed.next() {
+                Some(true) => {
+                    failed_positions.push(*position);
+                    false
+                }
+                _ => true,
+            });
+        }
+    }
+    Ok(failed_positions)
+}
+
+pub fn put_request(item: DdbItem) -> AResult<WriteRequest> {
+    Ok(WriteRequest::builder()
+        .put_request(PutRequest::builder().set_item(Some(item)).build()?)
+        .build())
+}
+
+pub fn delete_request(key: DdbItem) -> AResult<WriteRequest> {
+    Ok(WriteRequest::builder()
+        .delete_request(DeleteRequest::builder().set_key(Some(key)).build()?)
+        .build())
+}
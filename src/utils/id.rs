@@ -0,0 +1,82 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use rand::Rng;
+
+use super::time::TIMEZONE;
+
+// Crockford's base32, as used by ULID
+const ENCODING: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const ULID_LENGTH: usize = 26;
+const RANDOM_BITS: u32 = 80;
+const ID_SEPARATOR: char = '_';
+
+/// ULID of 48 bits of milliseconds since the epoch and 80 random bits, e.g.
+/// "01HWZ8Q4JQ1V4W6ZJ0Q0ZQ3K9T". ULIDs sort by time up to the millisecond.
+pub fn ulid<R: Rng>(now: DateTime<Utc>, rng: &mut R) -> String {
+    let millis = now.timestamp_millis().max(0) as u128;
+    let random = rng.gen::<u128>() & ((1 << RANDOM_BITS) - 1);
+    let value = (millis << RANDOM_BITS) | random;
+    (0..ULID_LENGTH)
+        .rev()
+        .map(|i| ENCODING[((value >> (i * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+/// Sort key of a new timestamped item: the local time of creation and a ULID, e.g.
+/// "2024-05-01T08:30:00+02:00_01HWZ8Q4JQ1V4W6ZJ0Q0ZQ3K9T". Keys created in the same
+/// second don't collide, and date range queries on the timestamp prefix keep working.
+pub fn new_id() -> String {
+    id_at(Utc::now())
+}
+
+/// Sort key of an item created at `time`, e.g. one imported from elsewhere.
+pub fn id_at(time: DateTime<Utc>) -> String {
+    format!(
+        "{}{}{}",
+        time.with_timezone(&TIMEZONE)
+            .to_rfc3339_opts(SecondsFormat::Secs, true),
+        ID_SEPARATOR,
+        ulid(time, &mut rand::thread_rng())
+    )
+}
+
+/// Timestamp part of a sort key. Keys created before ULIDs were added are plain timestamps.
+pub fn id_timestamp(sk: &str) -> &str {
+    sk.split_once(ID_SEPARATOR)
+        .map_or(sk, |(timestamp, _)| timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_ulid() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let now = DateTime::parse_from_rfc3339("2024-05-01T08:30:00.123+02:00")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let first = ulid(now, &mut rng);
+        let second = ulid(now, &mut rng);
+        assert_eq!(first.len(), ULID_LENGTH);
+        assert_ne!(first, second);
+        // the first 10 characters encode the time
+        assert_eq!(first[..10], second[..10]);
+        assert_eq!(&first[..10], "01HWSDGZNV");
+
+        let later = ulid(now + chrono::Duration::milliseconds(1), &mut rng);
+        assert!(later > first && later > second);
+    }
+
+    #[test]
+    fn test_id_timestamp() {
+        let id = new_id();
+        assert!(DateTime::parse_from_rfc3339(id_timestamp(&id)).is_ok());
+        assert_eq!(
+            id_timestamp("2024-05-01T08:30:00+02:00"),
+            "2024-05-01T08:30:00+02:00"
+        );
+    }
+}
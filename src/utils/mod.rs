@@ -1 +1,3 @@
+pub mod ddb;
+pub mod id;
 pub mod time;
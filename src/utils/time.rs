@@ -1,14 +1,136 @@
-use chrono::{Duration, Utc};
-use chrono_tz::Europe;
+use chrono::{Duration, NaiveDate, Utc};
+use chrono_tz::{Europe, Tz};
+use serde::{Deserialize, Serialize};
+
+/// Timezone of dates and day boundaries used across the vault.
+pub const TIMEZONE: Tz = Europe::Warsaw;
 
 pub fn get_today_datetime() -> String {
     Utc::now()
-        .with_timezone(&Europe::Warsaw)
+        .with_timezone(&TIMEZONE)
         .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
 }
 
 pub fn get_date_x_days_ago(x: i64) -> String {
-    (Utc::now().with_timezone(&Europe::Warsaw) + Duration::days(-x))
+    (Utc::now().with_timezone(&TIMEZONE) + Duration::days(-x))
         .format("%Y-%m-%d")
         .to_string()
 }
+
+pub fn get_today_date() -> NaiveDate {
+    Utc::now().with_timezone(&TIMEZONE).date_naive()
+}
+
+const DEFAULT_WINDOW_DAYS: i64 = 7;
+const MAX_WINDOW_DAYS: i64 = 366;
+
+/// Either `days` back from today, or dates `from` and `to`, e.g. "2024-05-01".
+#[derive(Deserialize, Default)]
+pub struct WindowParams {
+    pub days: Option<i64>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// Inclusive range of local dates.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DateWindow {
+    pub from: String, // e.g. "2024-05-01"
+    pub to: String,
+}
+
+impl DateWindow {
+    /// The last `days` days and today, so the default window of 7 is the last week.
+    pub fn last_days(days: i64, today: NaiveDate) -> DateWindow {
+        DateWindow {
+            from: (today - Duration::days(days)).to_string(),
+            to: today.to_string(),
+        }
+    }
+
+    /// Windows are at most a year long, so that one request can't read the whole table.
+    pub fn from_params(params: &WindowParams, today: NaiveDate) -> Result<DateWindow, String> {
+        match (params.days, &params.from, &params.to) {
+            (None, None, None) => Ok(DateWindow::last_days(DEFAULT_WINDOW_DAYS, today)),
+            (Some(days), None, None) => match (1..=MAX_WINDOW_DAYS).contains(&days) {
+                true => Ok(DateWindow::last_days(days, today)),
+                false => Err(format!("days must be between 1 and {}", MAX_WINDOW_DAYS)),
+            },
+            (None, Some(from), Some(to)) => {
+                let parse = |date: &str| {
+                    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                        .map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", date))
+                };
+                let (from, to) = (parse(from)?, parse(to)?);
+                if from > to {
+                    return Err(String::from("from must not be after to"));
+                }
+                if (to - from).num_days() > MAX_WINDOW_DAYS {
+                    return Err(format!(
+                        "Date range must not be longer than {} days",
+                        MAX_WINDOW_DAYS
+                    ));
+                }
+                Ok(DateWindow {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                })
+            }
+            (None, _, _) => Err(String::from("from and to must be given together")),
+            (Some(_), _, _) => Err(String::from("Use either days or from and to")),
+        }
+    }
+
+    /// Upper bound of sort keys that start with a date or a timestamp within the window.
+    pub fn sk_to(&self) -> String {
+        format!("{}~", self.to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(days: Option<i64>, from: Option<&str>, to: Option<&str>) -> WindowParams {
+        WindowParams {
+            days,
+            from: from.map(String::from),
+            to: to.map(String::from),
+        }
+    }
+
+    fn window(from: &str, to: &str) -> DateWindow {
+        DateWindow {
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_date_window() {
+        let today = NaiveDate::from_ymd_opt(2024, 5, 10).unwrap();
+        let from_params = |p: WindowParams| DateWindow::from_params(&p, today);
+
+        assert_eq!(
+            from_params(params(None, None, None)),
+            Ok(window("2024-05-03", "2024-05-10"))
+        );
+        assert_eq!(
+            from_params(params(Some(30), None, None)),
+            Ok(window("2024-04-10", "2024-05-10"))
+        );
+        assert_eq!(
+            from_params(params(None, Some("2024-01-01"), Some("2024-12-31"))),
+            Ok(window("2024-01-01", "2024-12-31"))
+        );
+        assert_eq!(window("2024-05-03", "2024-05-10").sk_to(), "2024-05-10~");
+
+        assert!(from_params(params(Some(0), None, None)).is_err());
+        assert!(from_params(params(Some(367), None, None)).is_err());
+        assert!(from_params(params(None, Some("2024-01-01"), Some("2025-01-02"))).is_err());
+        assert!(from_params(params(None, Some("2024-05-10"), Some("2024-05-01"))).is_err());
+        assert!(from_params(params(None, Some("2024-05-01"), None)).is_err());
+        assert!(from_params(params(Some(7), Some("2024-05-01"), Some("2024-05-02"))).is_err());
+        assert!(from_params(params(None, Some("May 1"), Some("2024-05-02"))).is_err());
+    }
+}